@@ -0,0 +1,73 @@
+#!/bin/sh
+set -e
+
+# usage: install-bundle-linux.sh [--downgrade] <package>...
+# packages of the bundle verified by the app, other files in its directory are
+# never installed. --downgrade allows a version older than the installed one, to
+# roll back an update
+
+command_exists() {
+	command -v "$@" > /dev/null 2>&1
+}
+
+# progress lines parsed by the app, see src/progress.rs
+progress() {
+	echo "::progress phase=$1 percent=$2"
+}
+
+do_install() {
+	echo "# Installing Sonaric from bundle"
+
+	RPM_FLAGS="--replacepkgs"
+	if [ "$1" = "--downgrade" ]; then
+		# dpkg installs older versions as is, rpm refuses them without --oldpackage
+		RPM_FLAGS="$RPM_FLAGS --oldpackage"
+		shift
+	fi
+	if [ $# -eq 0 ]; then
+		echo "ERROR: no packages to install" >&2
+		exit 1
+	fi
+	for package in "$@"; do
+		if [ ! -f "$package" ]; then
+			echo "ERROR: package $package not found" >&2
+			exit 1
+		fi
+	done
+
+	# the packages were verified by the app, install them without any repository
+	progress install-package 30
+	case "$1" in
+	*.deb)
+		if command_exists dpkg; then
+			DEBIAN_FRONTEND=noninteractive dpkg -i "$@"
+			PACKAGES_INSTALLED=1
+		fi
+		;;
+	*.rpm)
+		if command_exists rpm; then
+			rpm -Uvh $RPM_FLAGS "$@"
+			PACKAGES_INSTALLED=1
+		fi
+		;;
+	esac
+	if [ -z "$PACKAGES_INSTALLED" ]; then
+		echo "ERROR: the bundle has no packages for this distribution" >&2
+		exit 1
+	fi
+
+	progress wait-daemon 75
+	if command_exists systemctl; then
+		systemctl enable --now sonaricd || echo "::warning Failed to start sonaricd"
+	fi
+
+	for try in $(seq 1 30); do
+		sonaric version > /dev/null 2>&1 && break
+		echo "Waiting for Sonaric daemon to start..."
+		sleep 2
+	done
+	progress done 100
+	echo "Sonaric installed from bundle"
+}
+
+do_install "$@"
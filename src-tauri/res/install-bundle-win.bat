@@ -0,0 +1,60 @@
+@echo off
+
+set "bundleDir=%~1"
+
+REM WSL distribution, "Ubuntu-22.04" unless passed by the app
+set "distro=%~2"
+if "%distro%"=="" set "distro=Ubuntu-22.04"
+
+REM the packages verified by the app follow, other files in the bundle directory are never installed
+if "%~3"=="" (
+	echo No packages to install.
+	exit 1
+)
+
+if not exist "%bundleDir%" (
+	echo Bundle directory not found.
+	exit 1
+)
+
+REM the distribution can't be downloaded offline, it has to be installed already
+set "wslList=%temp%\wsl-list-%random%.tmp"
+wsl --list > %wslList%
+find "%distro%" %wslList% > nul
+if %errorlevel% neq 0 (
+	echo The %distro% WSL distribution is required for an offline install. Please install it and try again.
+	exit 1
+)
+
+wsl --list --running > %wslList%
+find "%distro%" %wslList% > nul
+if %errorlevel% neq 0 (
+    wsl --distribution %distro% --exec dbus-launch true
+	timeout /t 10 > nul
+)
+
+for /f "usebackq delims=" %%p in (`wsl -d %distro% --exec wslpath -a "%bundleDir%"`) do set "bundlePath=%%p"
+
+set "packages="
+:packages
+if "%~3"=="" goto install
+set "packages=%packages% '%bundlePath%/%~3'"
+shift /3
+goto packages
+
+:install
+echo ::progress phase=install-package percent=30
+echo Installing Sonaric from bundle...
+wsl -d %distro% --user root --exec /bin/bash -c "DEBIAN_FRONTEND=noninteractive dpkg -i%packages% && systemctl enable --now sonaricd"
+if %errorlevel% neq 0 (
+	echo Failed to install Sonaric from bundle. Please check the error message above and try again, or contact support.
+	exit 1
+)
+
+set "startupDir=%APPDATA%\Microsoft\Windows\Start Menu\Programs\Startup"
+if exist "%startupDir%" (
+    copy "%0\..\start-win.bat" "%startupDir%" > nul
+    setx SONARIC_WSL_DISTRIBUTION "%distro%" > nul
+)
+echo ::progress phase=done percent=100
+echo Sonaric installed from bundle
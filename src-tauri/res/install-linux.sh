@@ -2,14 +2,39 @@
 set -e
 APT_KEY_URL="https://us-central1-apt.pkg.dev/doc/repo-signing-key.gpg"
 APT_DOWNLOAD_URL="https://us-central1-apt.pkg.dev/projects/sonaric-platform"
-RPM_DOWNLOAD_URL="https://us-central1-yum.pkg.dev/projects/sonaric-platform/sonaric-releases-rpm"
 
-DOWNLOAD_URL="https://storage.googleapis.com/sonaric-releases/stable/linux/sonaric-amd64-latest.tar.gz"
+# release channel: stable, beta or nightly
+CHANNEL="${1:-stable}"
+case "$CHANNEL" in
+	stable)
+		REPO_NAME="sonaric-releases"
+	;;
+	beta|nightly)
+		REPO_NAME="sonaric-$CHANNEL"
+	;;
+	*)
+		echo "ERROR: Unknown release channel '$CHANNEL'" >&2
+		exit 1
+	;;
+esac
+
+RPM_DOWNLOAD_URL="https://us-central1-yum.pkg.dev/projects/sonaric-platform/$REPO_NAME-rpm"
+
+DOWNLOAD_URL="https://storage.googleapis.com/sonaric-releases/$CHANNEL/linux/sonaric-amd64-latest.tar.gz"
 
 command_exists() {
 	command -v "$@" > /dev/null 2>&1
 }
 
+# progress lines parsed by the app, see src/progress.rs
+progress() {
+	echo "::progress phase=$1 percent=$2"
+}
+
+warning() {
+	echo "::warning $*"
+}
+
 get_distribution() {
 	lsb_dist=""
 	# Every system that we officially support has /etc/os-release
@@ -78,8 +103,22 @@ check_forked() {
 	fi
 }
 
+add_rpm_repo() {
+	if grep -Fqs "[$REPO_NAME-rpm]" /etc/yum.repos.d/artifact-registry.repo; then
+		return
+	fi
+	$sh_c "tee -a /etc/yum.repos.d/artifact-registry.repo << EOF
+[$REPO_NAME-rpm]
+name=$REPO_NAME-rpm
+baseurl=$RPM_DOWNLOAD_URL
+enabled=1
+repo_gpgcheck=0
+gpgcheck=0
+EOF"
+}
+
 do_install() {
-	echo "# Executing Sonaric install script"
+	echo "# Executing Sonaric install script ($CHANNEL channel)"
 
 	user="$(id -un 2>/dev/null || true)"
 
@@ -100,10 +139,11 @@ do_install() {
 
 	# check if systemctl unit is present and if it is active
 	if command_exists systemctl && systemctl list-units --full --all sonaricd.service | grep -Fq 'sonaricd.service'; then
-    $sh_c 'systemctl start sonaricd' || echo "Failed to start sonaricd"
+    $sh_c 'systemctl start sonaricd' || warning "Failed to start sonaricd"
   fi
 
 	# perform some very rudimentary platform detection
+	progress detect-distro 5
 	lsb_dist=$( get_distribution )
 	lsb_dist="$(echo "$lsb_dist" | tr '[:upper:]' '[:lower:]')"
 
@@ -162,14 +202,23 @@ do_install() {
 	# Run setup for each distro accordingly
 	case "$lsb_dist" in
 		ubuntu|debian|raspbian)
+			apt_repo="deb [arch=$(dpkg --print-architecture) signed-by=/etc/apt/keyrings/sonaric.gpg] $APT_DOWNLOAD_URL $REPO_NAME-apt main"
       if command_exists sonaricd; then
         echo "Sonaric is already installed"
+        # follow the selected channel
+        progress add-repo 15
+        $sh_c "echo \"$apt_repo\" > /etc/apt/sources.list.d/sonaric.list"
+        progress update-index 30
         $sh_c 'apt-get update -qq >/dev/null'
+        progress install-package 50
         $sh_c 'apt-get install sonaricd sonaric'
+        progress wait-daemon 75
         for try in {1..30} ; do
           $sh_c "sonaric version" > /dev/null 2>&1 && break || sleep 2
         done
+        progress pull-gui 85
         $sh_c "sonaric update --nocolor --nofancy --all"
+        progress done 100
         exit 0
       fi
 
@@ -177,17 +226,20 @@ do_install() {
 			if ! command -v gpg > /dev/null; then
 				pre_reqs="$pre_reqs gnupg"
 			fi
-			apt_repo="deb [arch=$(dpkg --print-architecture) signed-by=/etc/apt/keyrings/sonaric.gpg] $APT_DOWNLOAD_URL sonaric-releases-apt main"
 			(
+				progress add-repo 10
 				$sh_c 'apt-get update -qq >/dev/null'
 				$sh_c "DEBIAN_FRONTEND=noninteractive apt-get install -y -qq $pre_reqs >/dev/null"
 				$sh_c 'install -m 0755 -d /etc/apt/keyrings'
 				$sh_c "curl -fsSL \"$APT_KEY_URL\" | gpg --dearmor --yes -o /etc/apt/keyrings/sonaric.gpg"
 				$sh_c "chmod a+r /etc/apt/keyrings/sonaric.gpg"
 				$sh_c "echo \"$apt_repo\" > /etc/apt/sources.list.d/sonaric.list"
+				progress update-index 30
 				$sh_c 'apt-get update -qq >/dev/null'
 			)
+			progress install-package 50
 			$sh_c "DEBIAN_FRONTEND=noninteractive apt-get install -y -qq sonaric >/dev/null"
+			progress done 100
 			exit 0
 			;;
 		centos|fedora|rhel|rocky)
@@ -200,36 +252,39 @@ do_install() {
 				pre_reqs="yum-utils"
 			fi
 
+      repo_opts="--disablerepo='sonaric-*' --enablerepo=$REPO_NAME-rpm"
       if command_exists sonaricd; then
         echo "Sonaric is already installed"
-        $sh_c "$pkg_manager update --refresh -y -q sonaricd sonaric"
-        $sh_c 'systemctl start sonaricd' || echo "Failed to start sonaricd"
+        progress add-repo 15
+        add_rpm_repo
+        progress install-package 40
+        $sh_c "$pkg_manager update --refresh -y -q $repo_opts sonaricd sonaric"
+        progress wait-daemon 75
+        $sh_c 'systemctl start sonaricd' || warning "Failed to start sonaricd"
         for try in {1..30} ; do
           $sh_c "sonaric version" > /dev/null 2>&1 && break || sleep 2
         done
+        progress pull-gui 85
         $sh_c "sonaric update --nocolor --nofancy --all"
+        progress done 100
         exit 0
       fi
 
-			repo_file_url="$RPM_DOWNLOAD_URL"
 			(
+				progress add-repo 10
 				$sh_c "$pkg_manager install -y -q $pre_reqs"
-				$sh_c 'tee -a /etc/yum.repos.d/artifact-registry.repo << EOF
-[sonaric-releases-rpm]
-name=sonaric-releases-rpm
-baseurl=https://us-central1-yum.pkg.dev/projects/sonaric-platform/sonaric-releases-rpm
-enabled=1
-repo_gpgcheck=0
-gpgcheck=0
-EOF'
+				add_rpm_repo
 
         # Enable the repository
+				progress update-index 30
 				$sh_c "$pkg_manager makecache"
 			)
 			(
+				progress install-package 50
 				pkgs="sonaricd sonaric"
-				$sh_c "$pkg_manager install -y -q $pkgs"
+				$sh_c "$pkg_manager install -y -q $repo_opts $pkgs"
 			)
+			progress done 100
 			exit 0
 			;;
 		*)
@@ -11,7 +11,21 @@ export HOMEBREW_NO_AUTO_UPDATE=1
 
 NAME="Sonaric app installer"
 SONARIC_OPTS="--nofancy --nocolor"
-SONARIC_PACKAGE_NAME="monk-io/sonaric/sonaric"
+# release channel: stable, beta or nightly
+SONARIC_CHANNEL="${1:-stable}"
+case "${SONARIC_CHANNEL}" in
+  stable)
+    SONARIC_TAP="monk-io/sonaric"
+    ;;
+  beta|nightly)
+    SONARIC_TAP="monk-io/sonaric-${SONARIC_CHANNEL}"
+    ;;
+  *)
+    echo "ERROR: Unknown release channel '${SONARIC_CHANNEL}'" >&2
+    exit 1
+    ;;
+esac
+SONARIC_PACKAGE_NAME="${SONARIC_TAP}/sonaric"
 SONARIC_SERVICE_NAME="sonaric"
 SONARIC_RUNTIME_SERVICE_NAME="sonaric-runtime"
 
@@ -24,6 +38,15 @@ abort() {
   exit 1
 }
 
+# progress lines parsed by the app, see src/progress.rs
+progress() {
+  echo "::progress phase=$1 percent=$2"
+}
+
+warn() {
+  echo "::warning $@"
+}
+
 execute() {
   if ! "$@"; then
     abort "Failed during: ${@}"
@@ -151,6 +174,7 @@ activate_app() {
   execute osascript -e "tell app \"${args}\" to activate"
 }
 
+progress detect-distro 5
 log "Check developer command line tools"
 if [[ "$(xcode-select -p 2>/dev/null)" == "" ]]; then
     log "Install developer command line tools"
@@ -197,6 +221,7 @@ if [ ! -x "$(command -v $(which brew 2>/dev/null))" ]; then
       echo "xcode-select --switch ${CLT_PATH}" >> "${SETUP_SCRIPT}"
   fi
 
+  progress add-repo 10
   log "Preparing to Homebrew installation"
   if [[ ! -d "${HOMEBREW_REPOSITORY}" ]]; then
     # osascript "-e do shell script \"install -d -o $(id -un) -g admin -m 0755 /usr/local/Homebrew\" with administrator privileges"
@@ -250,9 +275,19 @@ if [ ! -x "$(command -v $(which brew 2>/dev/null))" ]; then
   check_command brew
 fi
 
+progress update-index 30
 log "Fetching the newest version of Homebrew and installed packages"
 brew update -q -f || warn "Failed to execute: brew update"
 
+# switching channels replaces the formula from the other tap, the node data is kept
+INSTALLED_TAP="$(brew info --json=v2 --installed 2>/dev/null | grep -o '"full_name": *"[^"]*/sonaric"' | sed -e 's/.*"\(.*\)\/sonaric"/\1/' | head -n 1)"
+if [[ -n "${INSTALLED_TAP}" && "${INSTALLED_TAP}" != "${SONARIC_TAP}" ]]; then
+  log "Switching Sonaric from ${INSTALLED_TAP} to ${SONARIC_TAP}"
+  brew services stop -q ${SONARIC_SERVICE_NAME} || true
+  execute brew uninstall -q --ignore-dependencies "${INSTALLED_TAP}/sonaric"
+fi
+
+progress install-package 45
 if brew list -q ${SONARIC_PACKAGE_NAME} 2>/dev/null; then
   log "Sonaric upgrading to the newest version"
   execute brew upgrade -q -f --skip-cask-deps ${SONARIC_PACKAGE_NAME}
@@ -269,6 +304,7 @@ execute brew services stop -q ${SONARIC_SERVICE_NAME}
 log "Remove old versions, stale lock files and outdated downloads..."
 execute brew cleanup -q
 
+progress wait-daemon 70
 log "Service ${SONARIC_SERVICE_NAME} starting..."
 execute brew services start -q ${SONARIC_SERVICE_NAME}
 
@@ -303,6 +339,8 @@ execute brew services info -q ${SONARIC_SERVICE_NAME}
 log "Sonaric workloads list..."
 execute sonaric ${SONARIC_OPTS} ps -a
 
+progress pull-gui 85
 log "Sonaric workloads updating..."
 execute sonaric ${SONARIC_OPTS} update --all
+progress done 100
 
@@ -0,0 +1,81 @@
+#!/bin/sh
+set -e
+
+# release channel and version to reinstall, e.g. "stable 1.4.2"
+CHANNEL="${1:-stable}"
+VERSION="$2"
+
+case "$CHANNEL" in
+	stable)
+		REPO_NAME="sonaric-releases"
+	;;
+	*)
+		REPO_NAME="sonaric-$CHANNEL"
+	;;
+esac
+
+command_exists() {
+	command -v "$@" > /dev/null 2>&1
+}
+
+# progress lines parsed by the app, see src/progress.rs
+progress() {
+	echo "::progress phase=$1 percent=$2"
+}
+
+warning() {
+	echo "::warning $*"
+}
+
+# version of package $1 in the apt repository matching $VERSION, e.g. 1.4.2-1
+apt_version() {
+	apt-cache madison "$1" | awk -F'|' '{ gsub(/ /, "", $2); print $2 }' | grep -E "^v?$VERSION([-+~].*)?$" | head -n 1
+}
+
+do_rollback() {
+	echo "# Reinstalling Sonaric $VERSION ($CHANNEL channel)"
+
+	if [ -z "$VERSION" ]; then
+		echo "ERROR: no version to reinstall" >&2
+		exit 1
+	fi
+
+	if command_exists apt-get; then
+		progress update-index 20
+		apt-get update -qq >/dev/null
+		sonaricd_version="$(apt_version sonaricd)"
+		sonaric_version="$(apt_version sonaric)"
+		if [ -z "$sonaricd_version" ] || [ -z "$sonaric_version" ]; then
+			echo "ERROR: Sonaric $VERSION is no longer available in the package repository" >&2
+			exit 1
+		fi
+		progress install-package 50
+		DEBIAN_FRONTEND=noninteractive apt-get install -y -qq --allow-downgrades \
+			"sonaricd=$sonaricd_version" "sonaric=$sonaric_version"
+	elif command_exists dnf || command_exists yum; then
+		pkg_manager="dnf"
+		if ! command_exists dnf; then
+			pkg_manager="yum"
+		fi
+		progress install-package 50
+		$pkg_manager downgrade -y -q --disablerepo='sonaric-*' --enablerepo="$REPO_NAME-rpm" \
+			"sonaricd-$VERSION" "sonaric-$VERSION"
+	else
+		echo "ERROR: no supported package manager found" >&2
+		exit 1
+	fi
+
+	progress wait-daemon 75
+	if command_exists systemctl; then
+		systemctl restart sonaricd || warning "Failed to restart sonaricd"
+	fi
+	for try in $(seq 1 30); do
+		sonaric version > /dev/null 2>&1 && break
+		echo "Waiting for Sonaric daemon to start..."
+		sleep 2
+	done
+	progress done 100
+	echo "Sonaric $VERSION reinstalled"
+}
+
+do_rollback
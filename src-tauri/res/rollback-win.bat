@@ -0,0 +1,18 @@
+@echo off
+
+REM version to reinstall and release channel, passed by the app
+set "version=%~1"
+set "channel=%~2"
+
+REM WSL distribution, "Ubuntu-22.04" unless passed by the app
+set "distro=%~3"
+if "%distro%"=="" set "distro=Ubuntu-22.04"
+
+REM the distribution runs the Linux script, the packages come from its apt repository
+for /f "usebackq delims=" %%p in (`wsl -d %distro% --exec wslpath -a "%~dp0rollback-linux.sh"`) do set "scriptPath=%%p"
+
+wsl -d %distro% --user root --exec /bin/sh "%scriptPath%" %channel% %version%
+if %errorlevel% neq 0 (
+	echo Failed to reinstall Sonaric %version%. Please check the error message above and try again, or contact support.
+	exit 1
+)
@@ -0,0 +1,27 @@
+#!/bin/sh
+set -e
+
+command_exists() {
+	command -v "$@" > /dev/null 2>&1
+}
+
+do_start() {
+	echo "# Starting Sonaric daemon"
+
+	if ! command_exists systemctl; then
+		echo "ERROR: systemctl not found"
+		exit 1
+	fi
+
+	# polkit asks for the credentials if the user is not allowed to manage the unit
+	systemctl start sonaricd
+
+	for try in $(seq 1 30); do
+		sonaric version > /dev/null 2>&1 && break
+		echo "Waiting for Sonaric daemon to start..."
+		sleep 2
+	done
+	sonaric version --nocolor --nofancy
+}
+
+do_start
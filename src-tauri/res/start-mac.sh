@@ -0,0 +1,112 @@
+#!/bin/bash
+
+# We don't need return codes for "$(command)", only stdout is needed.
+# Allow `[[ -n "$(command)" ]]`, `func "$(command)"`, pipes, etc.
+# shellcheck disable=SC2312
+set -u
+
+export HOMEBREW_NO_COLOR=1
+export HOMEBREW_NO_EMOJI=1
+export HOMEBREW_NO_AUTO_UPDATE=1
+
+NAME="Sonaric app"
+SONARIC_OPTS="--nofancy --nocolor"
+SONARIC_SERVICE_NAME="sonaric"
+SONARIC_RUNTIME_SERVICE_NAME="sonaric-runtime"
+LAUNCHD_DOMAIN="gui/$(id -u)"
+
+log() {
+  echo "$@"
+}
+
+warn() {
+  echo "WARNING: $@"
+}
+
+abort() {
+  echo "ERROR: $@" >&2
+  exit 1
+}
+
+add_to_path(){
+  for arg in $@; do
+    if [ -d "${arg}" ]; then
+      if [[ "${PATH}" == "" ]]; then
+        PATH="${arg}"
+      else
+        case ":${PATH}:" in
+          *:"${arg}":*)
+            ;;
+          *)
+            PATH="${arg}:${PATH}"
+            ;;
+        esac
+      fi
+    fi
+  done
+}
+
+check_command(){
+  for arg in $@; do
+    local n="${arg}"
+    local p=$(which ${n} 2>/dev/null)
+    if [[ "${p}" == "" ]]; then
+      abort "${n} not found"
+    fi
+    if [ ! -x "$(command -v ${p})" ]; then
+      abort "${p} not executable"
+    fi
+  done
+}
+
+# Starts the launchd agent registered by `brew services`, falls back to brew if it is not registered
+start_service(){
+  local label="homebrew.mxcl.${1}"
+  local plist="${HOME}/Library/LaunchAgents/${label}.plist"
+  log "Service ${1} starting..."
+  if [ -f "${plist}" ]; then
+    launchctl bootstrap "${LAUNCHD_DOMAIN}" "${plist}" 2>/dev/null || true
+    launchctl kickstart "${LAUNCHD_DOMAIN}/${label}" || warn "Failed during: launchctl kickstart ${LAUNCHD_DOMAIN}/${label}"
+  else
+    brew services start -q ${1} || warn "Failed during: brew services start -q ${1}"
+  fi
+}
+
+# Fail fast with a concise message when not using bash
+# Single brackets are needed here for POSIX compatibility
+# shellcheck disable=SC2292
+if [ -z "${BASH_VERSION:-}" ]; then
+  abort "Bash is required to interpret this script."
+fi
+
+add_to_path /sbin /usr/sbin /usr/local/sbin
+add_to_path /bin /usr/bin /usr/local/bin
+add_to_path /opt/homebrew/bin
+
+check_command brew uname launchctl sonaric
+
+OS="$(uname 2>/dev/null)"
+# Check if we are on mac
+if [[ "${OS}" != "Darwin" ]]; then
+  abort "${NAME} is only supported on macOS."
+fi
+
+start_service ${SONARIC_RUNTIME_SERVICE_NAME}
+start_service ${SONARIC_SERVICE_NAME}
+
+ITR=0
+TOTAL_ITRS=50
+RUNNING=false
+while [[ "$RUNNING" != "true" && ${ITR} -le ${TOTAL_ITRS} ]]; do
+  ITR=$((ITR + 1))
+  if sonaric ${SONARIC_OPTS} version; then
+    RUNNING=true
+  else
+    log "${ITR}) Wait for Sonaric daemon to become started..."
+    sleep 5
+  fi
+done
+
+if [[ "$RUNNING" != "true" ]]; then
+  abort "Sonaric daemon did not start"
+fi
@@ -1,5 +1,11 @@
 @echo off
 
+REM WSL distribution, "Ubuntu-22.04" unless passed by the app or saved by the
+REM installer for the copy in the Startup folder
+set "distro=%~1"
+if "%distro%"=="" set "distro=%SONARIC_WSL_DISTRIBUTION%"
+if "%distro%"=="" set "distro=Ubuntu-22.04"
+
 set "tmpWslVersion=%temp%\wsl-version-%random%.tmp"
 wsl --version > %tmpWslVersion% 2> nul
 if %errorlevel% neq 0 (
@@ -8,16 +14,22 @@ if %errorlevel% neq 0 (
 
 set "wslList=%temp%\wsl-list-%random%.tmp"
 wsl --list > %wslList%
-find "Ubuntu-22.04" %wslList% > nul
+find "%distro%" %wslList% > nul
 if %errorlevel% neq 0 (
 	exit 0
 )
 
 wsl --list --running > %wslList%
-find "Ubuntu-22.04" %wslList% > nul
+find "%distro%" %wslList% > nul
 if %errorlevel% neq 0 (
-    wsl --distribution Ubuntu-22.04 --exec dbus-launch true
-    exit 0
+    wsl --distribution %distro% --exec dbus-launch true
+    timeout /t 5 > nul
 )
 
+wsl -d %distro% --user root --exec /bin/bash -c "systemctl start sonaricd"
+if %errorlevel% neq 0 (
+	echo Failed to start Sonaric.
+	exit 1
+)
 
+echo Sonaric started
@@ -1,5 +1,9 @@
 @echo off
 
+REM WSL distribution, "Ubuntu-22.04" unless passed by the app
+set "distro=%~1"
+if "%distro%"=="" set "distro=Ubuntu-22.04"
+
 REM ------------------------------------------------------
 REM Sonaric bash stop script
 setlocal EnableDelayedExpansion
@@ -17,7 +21,7 @@ REM ------------------------------------------------------
 
 
 echo Stopping Sonaric...
-wsl -d Ubuntu-22.04 --user root --exec /bin/bash -c "!stopScript!"
+wsl -d %distro% --user root --exec /bin/bash -c "!stopScript!"
 if %errorlevel% neq 0 (
 	echo Failed to stop Sonaric.
 	exit 1
@@ -1,5 +1,9 @@
 @echo off
 
+REM WSL distribution, "Ubuntu-22.04" unless passed by the app
+set "distro=%~1"
+if "%distro%"=="" set "distro=Ubuntu-22.04"
+
 REM ------------------------------------------------------
 REM Sonaric bash uninstall script
 setlocal EnableDelayedExpansion
@@ -27,7 +31,7 @@ REM ------------------------------------------------------
 
 
 echo Uninstalling Sonaric...
-wsl -d Ubuntu-22.04 --user root --exec /bin/bash -c "!uninstallScript!"
+wsl -d %distro% --user root --exec /bin/bash -c "!uninstallScript!"
 if %errorlevel% neq 0 (
 	echo Failed to uninstall Sonaric.
 	exit 1
@@ -0,0 +1,218 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+
+pub(crate) const MANIFEST: &str = "manifest.json";
+
+/// Describes the content of an offline bundle, stored as `manifest.json` next to the packages.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    pub version: String,
+    pub files: Vec<BundleFile>,
+}
+
+impl Manifest {
+    pub fn version(&self) -> Result<Version, Error> {
+        Version::parse(self.version.trim().trim_start_matches('v')).map_err(Error::from)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BundleFile {
+    pub name: String,
+    pub sha256: String,
+}
+
+/// A verified offline bundle, either a directory or an extracted `.tar.gz`.
+pub struct Bundle {
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+    // keeps the extracted archive until the install is done
+    _extracted: Option<tempfile::TempDir>,
+}
+
+impl Bundle {
+    /// Opens the bundle at `path` and verifies the checksums of all files in its manifest.
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        if path.is_dir() {
+            return Self::load(path.to_path_buf(), None);
+        }
+
+        let tmp = tempfile::Builder::new()
+            .prefix("sonaric-bundle")
+            .tempdir()?;
+        let archive = flate2::read::GzDecoder::new(File::open(path)?);
+        tar::Archive::new(archive).unpack(tmp.path())?;
+        Self::open_extracted(tmp)
+    }
+
+    /// Opens a bundle in a temporary directory that is removed with the bundle.
+    pub(crate) fn open_extracted(dir: tempfile::TempDir) -> Result<Self, Error> {
+        Self::load(dir.path().to_path_buf(), Some(dir))
+    }
+
+    fn load(dir: PathBuf, extracted: Option<tempfile::TempDir>) -> Result<Self, Error> {
+        let manifest: Manifest =
+            serde_json::from_str(&std::fs::read_to_string(dir.join(MANIFEST))?)?;
+        let bundle = Self {
+            dir,
+            manifest,
+            _extracted: extracted,
+        };
+        bundle.verify()?;
+
+        tracing::info!(
+            "bundle {:?} verified, version {}",
+            bundle.dir,
+            bundle.manifest.version
+        );
+        Ok(bundle)
+    }
+
+    pub fn version(&self) -> Result<Version, Error> {
+        self.manifest.version()
+    }
+
+    /// Returns the packages of the bundle with the given extension, e.g. `deb`.
+    pub fn packages(&self, extension: &str) -> Vec<PathBuf> {
+        self.manifest
+            .files
+            .iter()
+            .map(|f| self.dir.join(&f.name))
+            .filter(|p| p.extension().is_some_and(|ext| ext == extension))
+            .collect()
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        self.version()?;
+        if self.manifest.files.is_empty() {
+            return Err(Error::from(anyhow!("Bundle manifest lists no files")));
+        }
+
+        for file in &self.manifest.files {
+            // files must be next to the manifest, the names are passed to the install scripts
+            if !is_plain_name(&file.name) {
+                return Err(Error::Verification(format!(
+                    "invalid file in bundle: {}",
+                    file.name
+                )));
+            }
+
+            let digest = sha256_file(&self.dir.join(&file.name))?;
+            if !digest.eq_ignore_ascii_case(file.sha256.trim()) {
+                return Err(Error::Verification(format!(
+                    "checksum mismatch for {}: expected sha256:{}, got sha256:{}",
+                    file.name, file.sha256, digest
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Package file names like `sonaric_1.4.2_amd64.deb`, without paths or shell syntax.
+fn is_plain_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['.', '-'])
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._+~-".contains(c))
+}
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_bundle(dir: &Path, files: &[(&str, &str)], listed: &[&str]) {
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        let manifest = Manifest {
+            version: "1.4.2".to_string(),
+            files: listed
+                .iter()
+                .map(|name| BundleFile {
+                    name: name.to_string(),
+                    sha256: sha256_file(&dir.join(name)).unwrap_or_default(),
+                })
+                .collect(),
+        };
+        std::fs::write(
+            dir.join(MANIFEST),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn packages_are_listed_in_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        write_bundle(
+            dir.path(),
+            &[
+                ("sonaric_1.4.2_amd64.deb", "sonaric"),
+                ("sonaricd_1.4.2_amd64.deb", "sonaricd"),
+                ("extra_1.0_amd64.deb", "not verified"),
+            ],
+            &["sonaric_1.4.2_amd64.deb", "sonaricd_1.4.2_amd64.deb"],
+        );
+
+        let bundle = Bundle::open(dir.path()).unwrap();
+        assert_eq!(bundle.version().unwrap(), Version::new(1, 4, 2));
+        assert_eq!(
+            bundle.packages("deb"),
+            vec![
+                dir.path().join("sonaric_1.4.2_amd64.deb"),
+                dir.path().join("sonaricd_1.4.2_amd64.deb"),
+            ]
+        );
+        assert!(bundle.packages("rpm").is_empty());
+    }
+
+    #[test]
+    fn verify_rejects() {
+        let dir = tempfile::tempdir().unwrap();
+        write_bundle(dir.path(), &[("sonaric.deb", "sonaric")], &["sonaric.deb"]);
+        std::fs::write(dir.path().join("sonaric.deb"), "tampered").unwrap();
+        assert!(matches!(
+            Bundle::open(dir.path()),
+            Err(Error::Verification(_))
+        ));
+
+        for name in ["../sonaric.deb", "-x.deb", "a b.deb", "a';b.deb", ".deb"] {
+            let dir = tempfile::tempdir().unwrap();
+            write_bundle(dir.path(), &[], &[]);
+            let manifest = format!(
+                r#"{{"version": "1.4.2", "files": [{{"name": "{}", "sha256": ""}}]}}"#,
+                name
+            );
+            std::fs::write(dir.path().join(MANIFEST), manifest).unwrap();
+            assert!(
+                matches!(Bundle::open(dir.path()), Err(Error::Verification(_))),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::io::Write;
+
+use crate::context::{AppContext, EventSink};
+use crate::error::Error;
+use crate::state::NodeState;
+use crate::version::version_info;
+use crate::version_cache::Lookup;
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+/// `status`: Sonaric is installed but the daemon or the GUI is not running.
+pub const EXIT_NOT_RUNNING: i32 = 3;
+/// `status`: Sonaric or one of its components is not installed.
+pub const EXIT_NOT_INSTALLED: i32 = 4;
+
+const USAGE: &str = "Usage: tauri-app <command> [--json]
+
+Manages the Sonaric node without opening a window.
+
+Commands:
+  status                          Show the state of the node
+  install                         Install or update Sonaric
+  start                           Start the daemon
+  stop                            Stop the daemon
+  uninstall                       Uninstall Sonaric
+  rollback                        Reinstall the daemon version replaced by the
+                                  last update
+  version                         Show the versions of the components
+  diagnostics <path>              Write a support bundle to the zip file
+  report-bug <description> [--name <name>]
+                                  Send a bug report with the logs, or save it
+                                  to the downloads dir if telemetry is off
+
+Options:
+  --json                          Print the result as JSON
+
+Exit codes:
+  0  success, or the node is ready for `status`
+  1  the command failed
+  2  invalid usage
+  3  the daemon or the GUI is not running (`status`)
+  4  Sonaric is not installed (`status`)
+
+Without a command the app starts the window as usual.";
+
+#[derive(Debug)]
+pub enum Command {
+    Status,
+    Install,
+    Start,
+    Stop,
+    Uninstall,
+    Rollback,
+    Version,
+    Diagnostics { path: String },
+    ReportBug { name: String, description: String },
+}
+
+#[derive(Debug)]
+pub struct Cli {
+    pub command: Command,
+    pub json: bool,
+}
+
+impl Cli {
+    /// Parses the arguments without the program name. Returns `Ok(None)` if the first
+    /// argument isn't a command, e.g. the arguments the OS passes to the app, and an
+    /// empty error for `help`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut args = args.into_iter();
+        let command = match args.next().as_deref() {
+            Some("status") => Command::Status,
+            Some("install") => Command::Install,
+            Some("start") => Command::Start,
+            Some("stop") => Command::Stop,
+            Some("uninstall") => Command::Uninstall,
+            Some("rollback") => Command::Rollback,
+            Some("version") => Command::Version,
+            Some("diagnostics") => Command::Diagnostics {
+                path: String::new(),
+            },
+            Some("report-bug") => Command::ReportBug {
+                name: String::new(),
+                description: String::new(),
+            },
+            Some("help" | "--help" | "-h") => return Err(String::new()),
+            _ => return Ok(None),
+        };
+
+        let mut cli = Cli {
+            command,
+            json: false,
+        };
+        while let Some(arg) = args.next() {
+            match (&mut cli.command, arg.as_str()) {
+                (_, "--json") => cli.json = true,
+                (Command::ReportBug { name, .. }, "--name") => {
+                    *name = args.next().ok_or("--name requires a value")?;
+                }
+                (Command::Diagnostics { path }, _) if path.is_empty() && !arg.starts_with("--") => {
+                    *path = arg;
+                }
+                (Command::ReportBug { description, .. }, _) if !arg.starts_with("--") => {
+                    if !description.is_empty() {
+                        description.push(' ');
+                    }
+                    description.push_str(&arg);
+                }
+                _ => return Err(format!("unexpected argument: {}", arg)),
+            }
+        }
+
+        if let Command::Diagnostics { path } = &cli.command {
+            if path.is_empty() {
+                return Err("diagnostics requires a path".to_string());
+            }
+        }
+        if let Command::ReportBug { name, description } = &mut cli.command {
+            if description.is_empty() {
+                return Err("report-bug requires a description".to_string());
+            }
+            if name.is_empty() {
+                *name = std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .unwrap_or("cli".to_string());
+            }
+        }
+        Ok(Some(cli))
+    }
+
+    /// Prints the usage, with the parse error if any, and returns the exit code.
+    pub fn usage(error: &str) -> i32 {
+        if error.is_empty() {
+            println!("{}", USAGE);
+            return EXIT_OK;
+        }
+        eprintln!("error: {}\n\n{}", error, USAGE);
+        EXIT_USAGE
+    }
+
+    /// Prints the output of the scripts and the install progress to stderr,
+    /// stdout only holds the result of the command.
+    pub fn event_sink() -> EventSink {
+        Box::new(|event, payload| match event {
+            "install-output" | "status" => {
+                if let Some(line) = payload.as_str() {
+                    eprintln!("{}", line);
+                }
+            }
+            "install-progress" => {
+                if let Some(message) = payload["message"].as_str() {
+                    eprintln!("warning: {}", message);
+                } else if let (Some(phase), Some(percent)) =
+                    (payload["phase"].as_str(), payload["percent"].as_u64())
+                {
+                    eprintln!("[{:>3}%] {}", percent, phase);
+                }
+            }
+            _ => {}
+        })
+    }
+
+    pub async fn run(self, ctx: AppContext) -> i32 {
+        tracing::info!("cli command: {:?}", self.command);
+
+        match self.command {
+            Command::Status => self.status(ctx).await,
+            Command::Install => self.finish(crate::install(ctx).await),
+            Command::Start => self.finish(crate::start(ctx).await),
+            Command::Stop => self.finish(crate::stop(ctx).await),
+            Command::Uninstall => self.finish(crate::uninstall(ctx).await),
+            Command::Rollback => self.finish(crate::rollback::rollback(ctx).await),
+            Command::Version => {
+                let versions = version_info(ctx, Lookup::Cached).await;
+                if self.json {
+                    print_json(&versions);
+                } else {
+                    println!("Release channel: {}", versions.channel);
+                    println!("App version: {}", versions.app);
+                    println!("Daemon version: {}", versions.daemon);
+                    println!("GUI version: {}", versions.gui);
+                }
+                EXIT_OK
+            }
+            Command::Diagnostics { ref path } => {
+                let res = crate::diagnostics::export(ctx, std::path::Path::new(path)).await;
+                self.finish(res.map(|_| path.clone()))
+            }
+            Command::ReportBug {
+                ref name,
+                ref description,
+            } => {
+                let res = crate::send_bug_report(ctx, name.clone(), description.clone()).await;
+                self.finish(res)
+            }
+        }
+    }
+
+    async fn status(&self, ctx: AppContext) -> i32 {
+        let state = match ctx.platform().detect(ctx.clone()).await {
+            Ok(state) => state,
+            Err(e) => return self.finish::<String>(Err(e)),
+        };
+        let state = match state {
+            NodeState::Ready { .. } => {
+                let gui = crate::probe_gui(&ctx.settings()).await.is_ok();
+                state.with_gui(gui)
+            }
+            state => state,
+        };
+
+        if self.json {
+            print_json(&state);
+        } else {
+            println!("{}", describe(&state));
+        }
+        match state {
+            NodeState::Install { .. } => EXIT_NOT_INSTALLED,
+            NodeState::Start { .. } | NodeState::Update { daemon: false, .. } => EXIT_NOT_RUNNING,
+            NodeState::Update { .. } | NodeState::Ready { .. } => EXIT_OK,
+        }
+    }
+
+    fn finish<T: serde::Serialize + std::fmt::Display>(&self, res: Result<T, Error>) -> i32 {
+        let code = if res.is_ok() { EXIT_OK } else { EXIT_FAILURE };
+        if self.json {
+            match &res {
+                Ok(output) => print_json(&serde_json::json!({ "ok": true, "output": output })),
+                Err(e) => print_json(&serde_json::json!({ "ok": false, "error": e.to_string() })),
+            }
+        } else {
+            match &res {
+                Ok(output) => println!("{}", output.to_string().trim_end()),
+                Err(e) => eprintln!("error: {}", e),
+            }
+        }
+        code
+    }
+}
+
+fn describe(state: &NodeState) -> String {
+    match state {
+        NodeState::Install { missing, .. } => {
+            format!("Not installed: {:?} is missing", missing)
+        }
+        NodeState::Start {
+            installed,
+            daemon,
+            gui,
+            ..
+        } => format!(
+            "Installed ({}), daemon {}, GUI {}",
+            installed.as_deref().unwrap_or("unknown version"),
+            if *daemon { "running" } else { "not running" },
+            if *gui { "running" } else { "not running" },
+        ),
+        NodeState::Update {
+            installed,
+            latest,
+            daemon,
+            ..
+        } => format!(
+            "Update available: {} -> {}, daemon {}",
+            installed,
+            latest,
+            if *daemon { "running" } else { "not running" },
+        ),
+        NodeState::Ready { installed, .. } => format!(
+            "Ready ({})",
+            installed.as_deref().unwrap_or("unknown version")
+        ),
+    }
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(body) => {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "{}", body);
+        }
+        Err(e) => eprintln!("error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::context::Headless;
+    use crate::platform::fake::{FakeBackend, FakeState};
+
+    fn context(state: FakeState, gui_port: u16, dir: &tempfile::TempDir) -> AppContext {
+        let backend = FakeBackend::new(state, Some(semver::Version::new(1, 4, 0)));
+        let ctx = AppContext::Headless(Arc::new(Headless::test(Arc::new(backend), dir.path())));
+        ctx.update_settings(|s| s.gui_port = gui_port).unwrap();
+        ctx
+    }
+
+    fn cli(command: Command) -> Cli {
+        Cli {
+            command,
+            json: true,
+        }
+    }
+
+    /// Serves a page like the Sonaric GUI until the tests end, returns its port.
+    fn serve_gui() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 4096]);
+                let body = "<title>Sonaric</title>";
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        port
+    }
+
+    /// Returns a port nothing listens on.
+    fn closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[tokio::test]
+    async fn status_exit_codes() {
+        let gui = serve_gui();
+        let cases = [
+            (FakeState::Missing, gui, EXIT_NOT_INSTALLED),
+            (FakeState::Stopped, gui, EXIT_NOT_RUNNING),
+            // the daemon runs without a GUI
+            (FakeState::Running, closed_port(), EXIT_NOT_RUNNING),
+            (FakeState::Running, gui, EXIT_OK),
+        ];
+        for (state, port, code) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = context(state, port, &dir);
+            assert_eq!(cli(Command::Status).run(ctx).await, code, "{:?}", state);
+        }
+    }
+
+    #[tokio::test]
+    async fn lifecycle_exit_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(FakeState::Missing, serve_gui(), &dir);
+        let run = |command| cli(command).run(ctx.clone());
+
+        assert_eq!(run(Command::Start).await, EXIT_FAILURE);
+        assert_eq!(run(Command::Install).await, EXIT_OK);
+        assert_eq!(run(Command::Status).await, EXIT_OK);
+        assert_eq!(run(Command::Stop).await, EXIT_OK);
+        assert_eq!(run(Command::Status).await, EXIT_NOT_RUNNING);
+        assert_eq!(run(Command::Start).await, EXIT_OK);
+        assert_eq!(run(Command::Uninstall).await, EXIT_OK);
+        assert_eq!(run(Command::Status).await, EXIT_NOT_INSTALLED);
+    }
+}
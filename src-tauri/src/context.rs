@@ -0,0 +1,148 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tauri::api::path::{resolve_path, BaseDirectory};
+use tauri::{Config, Env, Manager, PackageInfo};
+
+use crate::error::Error;
+use crate::operation::Operations;
+use crate::platform::{Platform, PlatformBackend};
+use crate::settings::{Settings, SettingsStore};
+use crate::version_cache::VersionCache;
+
+/// Receives the events of a headless run, e.g. to print the install output.
+pub type EventSink = Box<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
+/// Runtime the lifecycle logic runs in, the Tauri app or the headless CLI.
+#[derive(Clone)]
+pub enum AppContext {
+    App(tauri::AppHandle),
+    Headless(Arc<Headless>),
+}
+
+/// State of a run without window, the counterpart of the managed state of the app.
+pub struct Headless {
+    pub config: Config,
+    pub package_info: PackageInfo,
+    pub platform: Arc<dyn PlatformBackend>,
+    pub settings: SettingsStore,
+    pub operations: Operations,
+    pub version_cache: VersionCache,
+    pub on_event: EventSink,
+}
+
+impl AppContext {
+    pub fn settings(&self) -> Settings {
+        match self {
+            AppContext::App(handle) => handle.state::<SettingsStore>().get(),
+            AppContext::Headless(h) => h.settings.get(),
+        }
+    }
+
+    pub fn update_settings(&self, f: impl FnOnce(&mut Settings)) -> Result<Settings, Error> {
+        match self {
+            AppContext::App(handle) => handle.state::<SettingsStore>().update(f),
+            AppContext::Headless(h) => h.settings.update(f),
+        }
+    }
+
+    pub fn operations(&self) -> &Operations {
+        match self {
+            AppContext::App(handle) => handle.state::<Operations>().inner(),
+            AppContext::Headless(h) => &h.operations,
+        }
+    }
+
+    pub fn version_cache(&self) -> VersionCache {
+        match self {
+            AppContext::App(handle) => handle.state::<VersionCache>().inner().clone(),
+            AppContext::Headless(h) => h.version_cache.clone(),
+        }
+    }
+
+    pub fn platform(&self) -> Arc<dyn PlatformBackend> {
+        match self {
+            AppContext::App(handle) => handle.state::<Platform>().0.clone(),
+            AppContext::Headless(h) => h.platform.clone(),
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        match self {
+            AppContext::App(handle) => handle.config().as_ref().clone(),
+            AppContext::Headless(h) => h.config.clone(),
+        }
+    }
+
+    pub fn package_info(&self) -> &PackageInfo {
+        match self {
+            AppContext::App(handle) => handle.package_info(),
+            AppContext::Headless(h) => &h.package_info,
+        }
+    }
+
+    /// Sends the event to the frontend, or to the event sink of a headless run.
+    pub fn emit<S: serde::Serialize + Clone>(&self, event: &str, payload: S) -> Result<(), Error> {
+        match self {
+            AppContext::App(handle) => handle.emit_all(event, payload)?,
+            AppContext::Headless(h) => (h.on_event)(event, serde_json::to_value(payload)?),
+        }
+        Ok(())
+    }
+
+    pub fn resolve_resource(&self, res: &str) -> Option<PathBuf> {
+        match self {
+            AppContext::App(handle) => handle.path_resolver().resolve_resource(res),
+            AppContext::Headless(h) => resolve_path(
+                &h.config,
+                &h.package_info,
+                &Env::default(),
+                res,
+                Some(BaseDirectory::Resource),
+            )
+            .ok(),
+        }
+    }
+
+    pub fn app_config_dir(&self) -> Option<PathBuf> {
+        tauri::api::path::app_config_dir(&self.config())
+    }
+
+    pub fn app_data_dir(&self) -> Option<PathBuf> {
+        tauri::api::path::app_data_dir(&self.config())
+    }
+
+    pub fn app_log_dir(&self) -> Option<PathBuf> {
+        tauri::api::path::app_log_dir(&self.config())
+    }
+}
+
+#[cfg(test)]
+impl Headless {
+    /// State of the unit tests, the settings are stored in `dir` and the events dropped.
+    /// The app dirs resolve to `dir` too, an absolute identifier replaces the base dir.
+    pub fn test(platform: Arc<dyn PlatformBackend>, dir: &std::path::Path) -> Self {
+        let mut config = Config::default();
+        config.tauri.bundle.identifier = dir.display().to_string();
+        Self {
+            config,
+            package_info: PackageInfo {
+                name: "sonaric-test".to_string(),
+                version: semver::Version::new(0, 1, 0),
+                authors: "",
+                description: "",
+            },
+            platform,
+            settings: SettingsStore::load(dir.join("settings.json")),
+            operations: Operations::default(),
+            version_cache: VersionCache::load(None),
+            on_event: Box::new(|_, _| {}),
+        }
+    }
+}
+
+impl From<tauri::AppHandle> for AppContext {
+    fn from(handle: tauri::AppHandle) -> Self {
+        AppContext::App(handle)
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::join;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::logfile;
+use crate::redact;
+use crate::settings::Settings;
+use crate::state::Backend;
+use crate::version::version_info;
+use crate::version_cache::Lookup;
+
+/// Only the end of larger log files is exported.
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(serde::Serialize)]
+struct SystemInfo {
+    os: &'static str,
+    family: &'static str,
+    arch: &'static str,
+    backend: Backend,
+    app_version: String,
+    collected_at: u64,
+}
+
+#[derive(serde::Serialize)]
+struct PortCheck {
+    name: &'static str,
+    url: String,
+    reachable: bool,
+    status: Option<u16>,
+    error: Option<String>,
+}
+
+/// Default file name offered in the save dialog.
+pub fn default_file_name() -> String {
+    format!("sonaric-diagnostics-{}.zip", unix_time())
+}
+
+pub fn report_file_name() -> String {
+    format!("sonaric-bug-report-{}.zip", unix_time())
+}
+
+/// Writes a support bundle to `dest`: the app and daemon logs, the versions, host
+/// and service information, port reachability and the redacted settings.
+pub async fn export(ctx: AppContext, dest: &Path) -> Result<(), Error> {
+    tracing::info!("export diagnostics to {:?}", dest);
+    write_bundle(ctx, dest, vec![]).await
+}
+
+/// Writes the support bundle with the bug report, for when telemetry is off.
+pub async fn export_report(
+    ctx: AppContext,
+    dest: &Path,
+    name: &str,
+    description: &str,
+) -> Result<(), Error> {
+    tracing::info!("export bug report to {:?}", dest);
+    let report = format!("Bug report by {}\n\n{}\n", name, description);
+    let entries = vec![(
+        "bug-report.txt".to_string(),
+        redact::redact(&report).into_bytes(),
+    )];
+    write_bundle(ctx, dest, entries).await
+}
+
+async fn write_bundle(
+    ctx: AppContext,
+    dest: &Path,
+    extra: Vec<(String, Vec<u8>)>,
+) -> Result<(), Error> {
+    let settings = ctx.settings();
+    let backend = ctx.platform();
+    let (versions, sections, ports, daemon_log_path) = join!(
+        version_info(ctx.clone(), Lookup::Cached),
+        backend.diagnostics(ctx.clone()),
+        check_ports(&settings),
+        logfile::daemon_log_path(&ctx),
+    );
+
+    let system = SystemInfo {
+        os: std::env::consts::OS,
+        family: std::env::consts::FAMILY,
+        arch: std::env::consts::ARCH,
+        backend: backend.kind(),
+        app_version: ctx.package_info().version.to_string(),
+        collected_at: unix_time(),
+    };
+
+    let mut entries: Vec<(String, Vec<u8>)> = vec![
+        (
+            "system.json".to_string(),
+            serde_json::to_vec_pretty(&system)?,
+        ),
+        (
+            "versions.json".to_string(),
+            serde_json::to_vec_pretty(&versions)?,
+        ),
+        ("ports.json".to_string(), serde_json::to_vec_pretty(&ports)?),
+        (
+            "settings.json".to_string(),
+            serde_json::to_vec_pretty(&settings.redacted())?,
+        ),
+    ];
+    entries.extend(extra);
+    for (name, body) in sections {
+        entries.push((
+            format!("platform/{}.txt", name),
+            redact::redact(&body).into_bytes(),
+        ));
+    }
+
+    let mut logs = vec![("logs/sonaricd.log".to_string(), daemon_log_path)];
+    if let Some(dir) = ctx.app_log_dir() {
+        for path in logfile::files(&dir, logfile::APP_LOG) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            logs.push((format!("logs/{}", name), Some(path.clone())));
+        }
+    }
+
+    let dest = dest.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let res = write_zip(&dest, entries, logs);
+        if res.is_err() {
+            let _ = std::fs::remove_file(&dest);
+        }
+        res
+    })
+    .await
+    .expect("should write diagnostics")
+}
+
+fn write_zip(
+    dest: &Path,
+    entries: Vec<(String, Vec<u8>)>,
+    logs: Vec<(String, Option<PathBuf>)>,
+) -> Result<(), Error> {
+    let mut zip = ZipWriter::new(File::create(dest)?);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for (name, body) in entries {
+        zip.start_file(name, options).map_err(anyhow::Error::from)?;
+        zip.write_all(&body)?;
+    }
+
+    // missing logs are noted in the bundle instead of failing the export
+    let mut errors = String::new();
+    for (name, path) in logs {
+        let path = match path {
+            Some(path) => path,
+            None => {
+                errors.push_str(&format!("{}: not available on this host\n", name));
+                continue;
+            }
+        };
+        match read_tail(&path, MAX_LOG_SIZE) {
+            Ok(body) => {
+                let body = redact::redact(&String::from_utf8_lossy(&body));
+                zip.start_file(name, options).map_err(anyhow::Error::from)?;
+                zip.write_all(body.as_bytes())?;
+            }
+            Err(e) => errors.push_str(&format!("{}: {:?}: {}\n", name, path, e)),
+        }
+    }
+    if !errors.is_empty() {
+        zip.start_file("errors.txt", options)
+            .map_err(anyhow::Error::from)?;
+        zip.write_all(errors.as_bytes())?;
+    }
+
+    zip.finish().map_err(anyhow::Error::from)?;
+    Ok(())
+}
+
+async fn check_ports(settings: &Settings) -> Vec<PortCheck> {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!("build http client: {}", e);
+            return vec![];
+        }
+    };
+
+    let mut checks = vec![];
+    for (name, url) in [
+        ("gui", settings.gui_url()),
+        ("api", format!("{}/version", settings.api_url())),
+    ] {
+        let check = match client.get(&url).send().await {
+            Ok(resp) => PortCheck {
+                name,
+                url,
+                reachable: true,
+                status: Some(resp.status().as_u16()),
+                error: None,
+            },
+            Err(e) => PortCheck {
+                name,
+                url,
+                reachable: false,
+                status: None,
+                error: Some(e.to_string()),
+            },
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+fn read_tail(path: &Path, limit: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(limit)))?;
+    let mut body = Vec::new();
+    file.read_to_end(&mut body)?;
+    Ok(body)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,308 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::bundle::{Bundle, Manifest, MANIFEST};
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::operation;
+
+/// Least interval between two `download-progress` events of a file.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, serde::Serialize)]
+pub struct DownloadProgress {
+    pub name: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+/// Downloads the packages of the latest release on the selected channel, verifies
+/// the signature of the release manifest and the checksums of the packages, and
+/// returns them as a bundle for `PlatformBackend::install_bundle`.
+pub async fn download_release(ctx: &AppContext, extensions: &[&str]) -> Result<Bundle, Error> {
+    let settings = ctx.settings();
+    let dir = tempfile::Builder::new()
+        .prefix("sonaric-download")
+        .tempdir()?;
+
+    let manifest_body = fetch(&settings.release_file_url(MANIFEST)).await?;
+    let signature_body =
+        fetch(&settings.release_file_url(&format!("{}.minisig", MANIFEST))).await?;
+    verify_signature(ctx, &manifest_body, &signature_body)?;
+
+    let mut manifest: Manifest = serde_json::from_slice(&manifest_body)?;
+    let version = manifest.version().map_err(|e| {
+        Error::Verification(format!(
+            "invalid release version {}: {}",
+            manifest.version, e
+        ))
+    })?;
+    // an older signed manifest must not replace a newer install
+    if let Ok(installed) = ctx.platform().daemon_version(ctx.clone()).await {
+        if version < installed {
+            return Err(Error::from(anyhow!(
+                "Sonaric {} is installed, the release {} is older",
+                installed,
+                version
+            )));
+        }
+    }
+
+    manifest.files.retain(|f| {
+        Path::new(&f.name)
+            .extension()
+            .is_some_and(|ext| extensions.iter().any(|e| ext == *e))
+    });
+    if manifest.files.is_empty() {
+        return Err(Error::from(anyhow!(
+            "Release {} has no packages for this platform",
+            manifest.version
+        )));
+    }
+
+    for file in &manifest.files {
+        if Path::new(&file.name).file_name() != Some(file.name.as_ref()) {
+            return Err(Error::Verification(format!(
+                "invalid file in manifest: {}",
+                file.name
+            )));
+        }
+        let url = settings.release_file_url(&file.name);
+        let digest = download(ctx, &url, &file.name, &dir.path().join(&file.name)).await?;
+        if !digest.eq_ignore_ascii_case(file.sha256.trim()) {
+            return Err(Error::Verification(format!(
+                "checksum mismatch for {}: expected sha256:{}, got sha256:{}",
+                file.name, file.sha256, digest
+            )));
+        }
+    }
+
+    // the bundle only lists the downloaded packages
+    std::fs::write(
+        dir.path().join(MANIFEST),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Bundle::open_extracted(dir)
+}
+
+async fn fetch(url: &str) -> Result<Vec<u8>, Error> {
+    let resp = reqwest::get(url).await?.error_for_status()?;
+    Ok(resp.bytes().await?.to_vec())
+}
+
+/// Streams `url` to `dest`, emitting throttled `download-progress` events, and returns
+/// the SHA-256 of the file.
+async fn download(ctx: &AppContext, url: &str, name: &str, dest: &Path) -> Result<String, Error> {
+    tracing::info!("download {} to {:?}", url, dest);
+    ctx.emit("install-output", format!("Downloading {}...", name))?;
+
+    let cancel = operation::current_token();
+    let mut resp = reqwest::get(url).await?.error_for_status()?;
+    let mut file = tokio::fs::File::create(dest).await?;
+    let mut hasher = Sha256::new();
+    let mut progress = DownloadProgress {
+        name: name.to_string(),
+        downloaded: 0,
+        total: resp.content_length(),
+    };
+    let mut emitted = Instant::now();
+    ctx.emit("download-progress", progress.clone())?;
+    while let Some(chunk) = resp.chunk().await? {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        file.write_all(&chunk).await?;
+        hasher.update(&chunk);
+        progress.downloaded += chunk.len() as u64;
+        if emitted.elapsed() >= PROGRESS_INTERVAL {
+            ctx.emit("download-progress", progress.clone())?;
+            emitted = Instant::now();
+        }
+    }
+    file.flush().await?;
+    ctx.emit("download-progress", progress)?;
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Verifies the minisign signature of `body` with the updater public key from `tauri.conf.json`.
+fn verify_signature(ctx: &AppContext, body: &[u8], signature: &[u8]) -> Result<(), Error> {
+    let pubkey = ctx.config().tauri.updater.pubkey.clone();
+    let pubkey = PublicKey::decode(&decode_base64_text(pubkey.as_bytes()))
+        .map_err(|e| Error::Verification(format!("invalid public key: {}", e)))?;
+    let signature = Signature::decode(&decode_base64_text(signature))
+        .map_err(|e| Error::Verification(format!("invalid signature: {}", e)))?;
+
+    pubkey
+        .verify(body, &signature, false)
+        .map_err(|e| Error::Verification(format!("signature mismatch: {}", e)))
+}
+
+/// Keys and signatures are published base64 encoded like the updater ones, or as plain minisign files.
+fn decode_base64_text(text: &[u8]) -> String {
+    let text = String::from_utf8_lossy(text);
+    base64::engine::general_purpose::STANDARD
+        .decode(text.trim())
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .unwrap_or_else(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+
+    use semver::Version;
+
+    use super::*;
+    use crate::context::Headless;
+    use crate::platform::fake::{FakeBackend, FakeState};
+
+    const MANIFEST_BODY: &str = include_str!("../fixtures/release/manifest.json");
+    const SIGNATURE: &str = include_str!("../fixtures/release/manifest.json.minisig");
+    const OTHER_KEY_SIGNATURE: &str = include_str!("../fixtures/release/other-key.minisig");
+    const PUBLIC_KEY: &str = include_str!("../fixtures/release/minisign.pub");
+
+    fn encode(text: &str) -> String {
+        base64::engine::general_purpose::STANDARD.encode(text)
+    }
+
+    fn context(pubkey: &str, installed: Option<Version>, dir: &tempfile::TempDir) -> AppContext {
+        let (state, version) = match installed {
+            Some(version) => (FakeState::Running, Some(version)),
+            None => (FakeState::Missing, None),
+        };
+        let mut headless = Headless::test(Arc::new(FakeBackend::new(state, version)), dir.path());
+        headless.config.tauri.updater.pubkey = pubkey.to_string();
+        AppContext::Headless(Arc::new(headless))
+    }
+
+    #[test]
+    fn signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let tampered = MANIFEST_BODY.replace("1.4.2", "1.4.3");
+        let cases = [
+            // (public key, body, signature, verified)
+            (
+                encode(PUBLIC_KEY),
+                MANIFEST_BODY,
+                SIGNATURE.to_string(),
+                true,
+            ),
+            (
+                PUBLIC_KEY.to_string(),
+                MANIFEST_BODY,
+                SIGNATURE.to_string(),
+                true,
+            ),
+            (encode(PUBLIC_KEY), MANIFEST_BODY, encode(SIGNATURE), true),
+            (encode(PUBLIC_KEY), &tampered, SIGNATURE.to_string(), false),
+            (
+                encode(PUBLIC_KEY),
+                MANIFEST_BODY,
+                OTHER_KEY_SIGNATURE.to_string(),
+                false,
+            ),
+            (
+                encode(PUBLIC_KEY),
+                MANIFEST_BODY,
+                "not a signature".to_string(),
+                false,
+            ),
+            (
+                "not a key".to_string(),
+                MANIFEST_BODY,
+                SIGNATURE.to_string(),
+                false,
+            ),
+        ];
+        for (pubkey, body, signature, verified) in cases {
+            let ctx = context(&pubkey, None, &dir);
+            match verify_signature(&ctx, body.as_bytes(), signature.as_bytes()) {
+                Ok(()) => assert!(verified, "{} {}", pubkey, signature),
+                Err(Error::Verification(e)) => assert!(!verified, "{}", e),
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn base64_text() {
+        let cases = [
+            (encode(PUBLIC_KEY), PUBLIC_KEY.to_string()),
+            (
+                format!("  {}\n", encode(PUBLIC_KEY)),
+                PUBLIC_KEY.to_string(),
+            ),
+            (PUBLIC_KEY.to_string(), PUBLIC_KEY.to_string()),
+            // base64 of bytes that aren't text
+            ("//79".to_string(), "//79".to_string()),
+        ];
+        for (text, decoded) in cases {
+            assert_eq!(decode_base64_text(text.as_bytes()), decoded, "{}", text);
+        }
+    }
+
+    /// Serves the signed release with `package` as the content of its package, returns
+    /// the releases URL.
+    fn serve_release(package: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut buf = [0; 4096];
+                let n = stream.read(&mut buf).unwrap_or_default();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                let (status, body) = match path.rsplit('/').next().unwrap_or_default() {
+                    "manifest.json" => ("200 OK", MANIFEST_BODY),
+                    "manifest.json.minisig" => ("200 OK", SIGNATURE),
+                    "sonaric_1.4.2_amd64.deb" => ("200 OK", package),
+                    _ => ("404 Not Found", ""),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn release() {
+        let cases = [
+            // (package content, installed version, error)
+            ("sonaric", None, None),
+            ("sonaric", Some(Version::new(1, 4, 2)), None),
+            ("tampered", None, Some("checksum mismatch")),
+            ("sonaric", Some(Version::new(1, 5, 0)), Some("is older")),
+        ];
+        for (package, installed, error) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = context(PUBLIC_KEY, installed, &dir);
+            let url = serve_release(package);
+            ctx.update_settings(|s| s.releases_url = url).unwrap();
+
+            match (download_release(&ctx, &["deb"]).await, error) {
+                (Ok(bundle), None) => {
+                    assert_eq!(bundle.version().unwrap(), Version::new(1, 4, 2));
+                    assert_eq!(bundle.packages("deb").len(), 1);
+                }
+                (Err(e), Some(error)) => assert!(e.to_string().contains(error), "{}", e),
+                (res, _) => panic!("{} {:?}: {:?}", package, error, res.map(|b| b.dir)),
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use tauri::updater;
 
+use crate::operation::OperationKind;
+
 // create the error type that represents all errors possible in our program
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -30,6 +32,15 @@ pub enum Error {
     #[error("{0}")]
     RetryError(&'static str),
 
+    #[error("verification failed: {0}")]
+    Verification(String),
+
+    #[error("operation cancelled")]
+    Cancelled,
+
+    #[error("{0} is already in progress")]
+    Busy(OperationKind),
+
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
 }
@@ -42,7 +53,7 @@ impl serde::Serialize for Error {
     {
         // skip sending specific error types to the sentry
         match self {
-            Error::RetryError(_) => {}
+            Error::RetryError(_) | Error::Cancelled | Error::Busy(_) => {}
             _ => {
                 tracing::error!("{:?}", self);
             }
@@ -0,0 +1,255 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::Manager;
+use tokio::join;
+
+use crate::error::Error;
+use crate::helper::try_url;
+use crate::operation::Operations;
+use crate::platform::platform;
+use crate::settings::settings;
+use crate::state::NodeState;
+use crate::tray;
+
+const DEFAULT_INTERVAL_SECS: u64 = 10;
+const MIN_INTERVAL_SECS: u64 = 2;
+
+/// Number of consecutive samples required before a new state is reported.
+const DEBOUNCE_SAMPLES: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    /// The daemon and both GUI endpoints respond.
+    Up,
+    /// The daemon responds, the GUI or its API does not.
+    Degraded,
+    /// The daemon does not respond.
+    Down,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct HealthReport {
+    pub state: Health,
+    pub daemon: bool,
+    pub gui: bool,
+    pub api: bool,
+}
+
+impl HealthReport {
+    fn new(daemon: bool, gui: bool, api: bool) -> Self {
+        let state = match (daemon, gui && api) {
+            (false, _) => Health::Down,
+            (true, false) => Health::Degraded,
+            (true, true) => Health::Up,
+        };
+        Self {
+            state,
+            daemon,
+            gui,
+            api,
+        }
+    }
+}
+
+/// Managed state of the background health monitor.
+pub struct HealthMonitor {
+    interval: AtomicU64,
+    last: Mutex<Option<HealthReport>>,
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self {
+            interval: AtomicU64::new(DEFAULT_INTERVAL_SECS),
+            last: Mutex::new(None),
+        }
+    }
+}
+
+/// Reports a state change only once it was seen `DEBOUNCE_SAMPLES` times in a row.
+#[derive(Default)]
+struct Debouncer {
+    current: Option<Health>,
+    pending: Option<(Health, u32)>,
+}
+
+impl Debouncer {
+    fn push(&mut self, sample: Health) -> Option<Health> {
+        if self.current.is_none() {
+            // report the first sample right away
+            self.current = Some(sample);
+            return self.current;
+        }
+        if self.current == Some(sample) {
+            self.pending = None;
+            return None;
+        }
+
+        let count = match self.pending {
+            Some((state, count)) if state == sample => count + 1,
+            _ => 1,
+        };
+        if count < DEBOUNCE_SAMPLES {
+            self.pending = Some((sample, count));
+            return None;
+        }
+
+        self.pending = None;
+        self.current = Some(sample);
+        self.current
+    }
+}
+
+pub fn spawn(handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut debouncer = Debouncer::default();
+        loop {
+            let report = probe(handle.clone()).await;
+            if let Some(state) = debouncer.push(report.state) {
+                let report = HealthReport { state, ..report };
+                tracing::info!("node health: {:?}", report);
+                if let Err(e) = publish(&handle, report).await {
+                    tracing::warn!("publish node health: {}", e);
+                }
+            }
+
+            let interval = handle
+                .state::<HealthMonitor>()
+                .interval
+                .load(Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+        }
+    });
+}
+
+async fn probe(handle: tauri::AppHandle) -> HealthReport {
+    let backend = platform(&handle);
+    let settings = settings(&handle);
+    let gui_url = settings.gui_url();
+    let api_url = format!("{}/version", settings.api_url());
+    let (daemon, gui, api) = join!(
+        backend.daemon_running(handle.clone().into()),
+        try_url(&gui_url),
+        try_url(&api_url),
+    );
+
+    HealthReport::new(
+        daemon,
+        gui.is_ok_and(|body| body.contains("Sonaric")),
+        api.is_ok(),
+    )
+}
+
+async fn publish(handle: &tauri::AppHandle, report: HealthReport) -> Result<(), Error> {
+    tray::set_health(handle, report.state)?;
+    handle.emit_all("node-health", report.clone())?;
+    *handle.state::<HealthMonitor>().last.lock().unwrap() = Some(report.clone());
+
+    // the running operation owns the menu, e.g. an install disables it until it's done
+    let idle = handle.state::<Operations>().list().is_empty();
+    if let (true, Some(window)) = (idle, handle.get_window("main")) {
+        let node = platform(handle).detect(handle.clone().into()).await?;
+        let menu = window.menu_handle();
+        for (id, enabled) in menu_items(report.state, &node) {
+            menu.get_item(id).set_enabled(enabled)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether the start, stop and uninstall items of the window menu are enabled.
+fn menu_items(health: Health, node: &NodeState) -> [(&'static str, bool); 3] {
+    let installed = !matches!(node, NodeState::Install { .. });
+    [
+        ("start", installed && health != Health::Up),
+        ("stop", health != Health::Down),
+        ("uninstall", installed),
+    ]
+}
+
+#[tauri::command]
+pub fn get_node_health(monitor: tauri::State<'_, HealthMonitor>) -> Option<HealthReport> {
+    monitor.last.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_health_interval(monitor: tauri::State<'_, HealthMonitor>, seconds: u64) {
+    tracing::info!("handle set_health_interval: {}s", seconds);
+    monitor
+        .interval
+        .store(seconds.max(MIN_INTERVAL_SECS), Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Backend, Component};
+
+    #[test]
+    fn debounce() {
+        use Health::*;
+
+        let cases: [(&[Health], &[Option<Health>]); 6] = [
+            // the first sample is reported right away
+            (&[Up, Up, Up], &[Some(Up), None, None]),
+            (&[Up, Down, Down, Down], &[Some(Up), None, Some(Down), None]),
+            // single flaps are suppressed
+            (
+                &[Up, Down, Up, Down, Up],
+                &[Some(Up), None, None, None, None],
+            ),
+            (
+                &[Up, Degraded, Down, Down],
+                &[Some(Up), None, None, Some(Down)],
+            ),
+            (
+                &[Down, Degraded, Degraded, Up, Degraded],
+                &[Some(Down), None, Some(Degraded), None, None],
+            ),
+            (
+                &[Up, Down, Down, Up, Up],
+                &[Some(Up), None, Some(Down), None, Some(Up)],
+            ),
+        ];
+        for (samples, reported) in cases {
+            let mut debouncer = Debouncer::default();
+            let pushed: Vec<_> = samples.iter().map(|s| debouncer.push(*s)).collect();
+            assert_eq!(pushed, reported, "{:?}", samples);
+        }
+    }
+
+    #[test]
+    fn menu() {
+        let missing = NodeState::install(Backend::Linux, Component::Sonaric);
+        let stopped = NodeState::Start {
+            backend: Backend::Linux,
+            installed: Some("1.4.2".to_string()),
+            daemon: false,
+            gui: false,
+        };
+        let ready = NodeState::Ready {
+            backend: Backend::Linux,
+            installed: Some("1.4.2".to_string()),
+            latest: None,
+        };
+        let cases = [
+            // (health, node, start, stop, uninstall)
+            (Health::Down, &missing, false, false, false),
+            (Health::Down, &stopped, true, false, true),
+            (Health::Degraded, &ready, true, true, true),
+            (Health::Up, &ready, false, true, true),
+        ];
+        for (health, node, start, stop, uninstall) in cases {
+            assert_eq!(
+                menu_items(health, node),
+                [("start", start), ("stop", stop), ("uninstall", uninstall)],
+                "{:?} {:?}",
+                health,
+                node
+            );
+        }
+    }
+}
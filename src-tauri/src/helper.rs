@@ -1,13 +1,9 @@
+use crate::context::AppContext;
 use crate::error::Error;
-use std::io::{BufRead, BufReader};
-use std::process::{Command, Stdio};
-use tauri::Manager;
-
-#[cfg(windows)]
-use std::os::windows::process::CommandExt;
-
-#[cfg(windows)]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
+use crate::operation;
+use crate::progress::ProgressParser;
+use crate::runner::CommandRunner;
+use anyhow::anyhow;
 
 #[derive(Clone)]
 pub(crate) struct ScriptOutput {
@@ -22,21 +18,17 @@ pub(crate) async fn try_url(url: &str) -> Result<String, Error> {
 }
 
 pub(crate) async fn copy_and_exec(
-    handle: tauri::AppHandle,
+    ctx: AppContext,
+    runner: &dyn CommandRunner,
     src: &str,
     dest: &str,
+    args: Vec<&str>,
 ) -> Result<String, Error> {
     std::fs::copy(src, dest)?;
 
-    match exec_script(
-        handle,
-        "/usr/bin/pkexec",
-        vec!["/usr/bin/sh", dest],
-        true,
-        true,
-    )
-    .await
-    {
+    let mut pkexec_args = vec!["/usr/bin/sh", dest];
+    pkexec_args.extend(args);
+    match exec_script(ctx, runner, "/usr/bin/pkexec", pkexec_args, true, true).await {
         Ok(output) => Ok(output),
         Err(e) => {
             // check error with causes if it countains code 126
@@ -52,113 +44,116 @@ pub(crate) async fn copy_and_exec(
 }
 
 pub(crate) async fn exec_script(
-    handle: tauri::AppHandle,
+    ctx: AppContext,
+    runner: &dyn CommandRunner,
     cmd: &str,
     args: Vec<&str>,
     emit_event: bool,
     check_status: bool,
 ) -> Result<String, Error> {
-    let mut h = duct::cmd(cmd, args).stderr_to_stdout().stdout_capture();
-    if !check_status {
-        h = h.unchecked();
-    }
-
-    let reader = h.reader()?;
-
-    let mut output = String::new();
-    for line in BufReader::new(reader).lines() {
-        let ln = match line {
-            Ok(ln) => ln,
-            Err(e) => {
-                // get last 8 lines from output
-                let mut last_output = output.lines().rev().take(8).collect::<Vec<&str>>();
-                last_output.reverse();
-
-                // append output to error
-                return Err(Error::from(
-                    anyhow::Error::new(e)
-                        .context(output.clone())
-                        .context(format!(
-                            "command execution failed:\n\n{}",
-                            last_output.join("\n"),
-                        )),
-                ));
-            }
-        };
+    let cancel = operation::current_token();
+    let mut progress = ProgressParser::default();
+    runner.stream(cmd, &args, check_status, &cancel, &mut |ln| {
         // print to console
-        tracing::debug!("{}", ln.clone());
+        tracing::debug!("{}", ln);
         if emit_event {
+            if let Some(p) = progress.feed(ln) {
+                ctx.emit("install-progress", p)?;
+            }
             // send to frontend
-            handle.emit_all("install-output", ln.clone())?;
+            if !ProgressParser::is_marker(ln) {
+                ctx.emit("install-output", ln.to_string())?;
+            }
         }
-        // append to output result
-        output.push_str(format!("{}\n", ln).as_str());
-    }
-    Ok(output)
+        Ok(())
+    })
 }
 
-pub(crate) async fn exec_cmd_script(args: Vec<&str>) -> Result<ScriptOutput, Error> {
-    let mut command = Command::new("cmd");
-    command.args(args).stdout(Stdio::piped());
+pub(crate) async fn exec_cmd_script(
+    runner: &dyn CommandRunner,
+    args: Vec<&str>,
+) -> Result<ScriptOutput, Error> {
+    let output = runner.output("cmd", &args)?;
+    Ok(ScriptOutput {
+        success: output.success(),
+        stdout: decode_utf16(&output.stdout)?,
+        stderr: String::new(),
+    })
+}
 
-    #[cfg(windows)]
-    command.creation_flags(CREATE_NO_WINDOW);
+pub(crate) async fn exec_cmd_bash_script(
+    runner: &dyn CommandRunner,
+    args: Vec<&str>,
+) -> Result<ScriptOutput, Error> {
+    let output = runner.output("cmd", &args)?;
+    Ok(ScriptOutput {
+        success: output.success(),
+        stdout: String::from_utf8(output.stdout)?,
+        stderr: String::from_utf8(output.stderr)?,
+    })
+}
 
-    let child = command.spawn()?;
-    let output = child.wait_with_output()?;
-    let u16s: Vec<u16> = output
-        .stdout
+/// Decodes the UTF-16LE output of `wsl.exe`, a trailing odd byte is ignored.
+pub(crate) fn decode_utf16(bytes: &[u8]) -> Result<String, Error> {
+    let u16s: Vec<u16> = bytes
         .chunks_exact(2)
-        .map(|chunk| u16::from_ne_bytes([chunk[0], chunk[1]]))
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
         .collect();
-    let stdout = match String::from_utf16(&u16s) {
-        Ok(stdout) => stdout,
-        Err(e) => return Err(Error::from(e)),
-    };
-    Ok(ScriptOutput {
-        success: output.status.success(),
-        stdout: stdout,
-        stderr: String::new(),
-    })
+    String::from_utf16(&u16s).map_err(Error::from)
 }
 
-pub(crate) async fn exec_cmd_bash_script(args: Vec<&str>) -> Result<ScriptOutput, Error> {
-    let mut command = Command::new("cmd");
-    command
-        .args(args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+pub(crate) fn get_resource_path(ctx: AppContext, res: &str) -> Result<String, Error> {
+    let resource_path = ctx
+        .resolve_resource(res)
+        .ok_or(anyhow!("Invalid install path"))?;
 
     #[cfg(windows)]
-    command.creation_flags(CREATE_NO_WINDOW);
-
-    let child = command.spawn()?;
-    let output = child.wait_with_output()?;
-    let stdout = match String::from_utf8(output.stdout) {
-        Ok(stdout) => stdout,
-        Err(e) => return Err(Error::from(e)),
-    };
-    let stderr = match String::from_utf8(output.stderr) {
-        Ok(stdout) => stdout,
-        Err(e) => return Err(Error::from(e)),
-    };
-    Ok(ScriptOutput {
-        success: output.status.success(),
-        stdout: stdout,
-        stderr: stderr,
-    })
+    let resource_path = std::path::Path::new(
+        resource_path
+            .as_path()
+            .to_string_lossy()
+            .trim_start_matches(r"\\?\"),
+    )
+    .to_path_buf();
+
+    let resource_path_str = resource_path
+        .as_path()
+        .to_str()
+        .ok_or(anyhow!("Invalid resource path"))?;
+
+    Ok(resource_path_str.to_string())
 }
 
-pub(crate) async fn is_wsl_running() -> bool {
-    let installed = match exec_cmd_script(vec!["/C", "wsl", "--version"]).await {
-        Ok(res) => res.success,
-        _ => false,
-    };
-    if !installed {
-        return false;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
     }
-    match exec_cmd_script(vec!["/C", "wsl", "--list", "--running"]).await {
-        Ok(res) => res.success && res.stdout.contains("Ubuntu-22.04"),
-        _ => false,
+
+    #[test]
+    fn decode_utf16_output() {
+        let mut odd = utf16le("Ubuntu");
+        odd.push(0x0a);
+        let cases = [
+            (
+                utf16le("Ubuntu-22.04 (Default)\r\n"),
+                "Ubuntu-22.04 (Default)\r\n",
+            ),
+            (
+                utf16le("Standardverteilung: Ubuntu"),
+                "Standardverteilung: Ubuntu",
+            ),
+            (utf16le(""), ""),
+            // a trailing odd byte is ignored
+            (odd, "Ubuntu"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(decode_utf16(&bytes).unwrap(), expected);
+        }
+
+        // unpaired surrogate
+        assert!(decode_utf16(&[0x00, 0xd8]).is_err());
     }
 }
@@ -0,0 +1,395 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::loglevel::DEFAULT_LEVEL;
+
+/// Name of the current app log, the rotated ones are `app.log.1` (newest) to `app.log.N`.
+pub const APP_LOG: &str = "app.log";
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+static CONFIG: RwLock<Option<LogSettings>> = RwLock::new(None);
+
+/// When the app log is rotated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rotation {
+    /// On the first write after midnight (UTC).
+    Daily,
+    /// When the file reaches `max_size_mb`.
+    #[default]
+    Size,
+}
+
+/// Log part of the settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    pub rotation: Rotation,
+    /// Size cap of a log file, also applies to daily rotation.
+    pub max_size_mb: u64,
+    /// Number of rotated files kept besides the current one.
+    pub max_files: usize,
+    /// Filter directives of the console log, e.g. `info,sonaric=trace`.
+    pub console_level: String,
+    /// Filter directives of the app log file.
+    pub file_level: String,
+}
+
+impl LogSettings {
+    fn max_size(&self) -> u64 {
+        self.max_size_mb.saturating_mul(1024 * 1024)
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            rotation: Rotation::default(),
+            max_size_mb: 10,
+            max_files: 5,
+            console_level: DEFAULT_LEVEL.to_string(),
+            file_level: DEFAULT_LEVEL.to_string(),
+        }
+    }
+}
+
+/// Applies the settings to the next write of the log files.
+pub fn configure(settings: &LogSettings) {
+    *CONFIG.write().unwrap() = Some(settings.clone());
+}
+
+/// Log file rotated by day or size, keeping `max_files` rotated files.
+pub struct RollingFile {
+    dir: PathBuf,
+    name: String,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    file: Option<File>,
+    size: u64,
+    // UTC day the file was last written on
+    day: u64,
+}
+
+impl RollingFile {
+    pub fn new(dir: &Path, name: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            name: name.to_string(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(&self.name)
+    }
+
+    fn open(&self, state: &mut State) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path())?;
+        let meta = file.metadata()?;
+        state.size = meta.len();
+        state.day = meta.modified().map(day).unwrap_or_else(|_| today());
+        state.file = Some(file);
+        Ok(())
+    }
+
+    fn rotate(&self, state: &mut State, max_files: usize) -> std::io::Result<()> {
+        state.file = None;
+
+        // drop the oldest files, including the ones beyond a lowered limit
+        for (index, path) in rotated(&self.dir, &self.name) {
+            if index >= max_files {
+                fs::remove_file(path)?;
+            }
+        }
+        for index in (1..max_files).rev() {
+            let from = self.dir.join(format!("{}.{}", self.name, index));
+            if from.exists() {
+                fs::rename(from, self.dir.join(format!("{}.{}", self.name, index + 1)))?;
+            }
+        }
+        if max_files > 0 {
+            fs::rename(self.path(), self.dir.join(format!("{}.1", self.name)))?;
+        } else {
+            fs::remove_file(self.path())?;
+        }
+
+        self.open(state)
+    }
+
+    fn write(&self, buf: &[u8]) -> std::io::Result<usize> {
+        let config = CONFIG.read().unwrap().clone().unwrap_or_default();
+        self.append(buf, &config, today())
+    }
+
+    /// Writes `buf` on the UTC day `today`, rotating first if the settings call for it.
+    fn append(&self, buf: &[u8], config: &LogSettings, today: u64) -> std::io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.file.is_none() {
+            self.open(&mut state)?;
+        }
+
+        // an empty file is never rotated
+        let due = state.size > 0
+            && ((config.rotation == Rotation::Daily && state.day != today)
+                || state.size + buf.len() as u64 > config.max_size());
+        if due {
+            // keep logging to the current file, the writer can't log its own errors
+            if let Err(e) = self.rotate(&mut state, config.max_files) {
+                eprintln!("rotate {:?}: {}", self.path(), e);
+            }
+            if state.file.is_none() {
+                self.open(&mut state)?;
+            }
+        }
+
+        let file = state.file.as_mut().expect("log file should be open");
+        let n = file.write(buf)?;
+        state.size += n as u64;
+        state.day = today;
+        Ok(n)
+    }
+}
+
+impl<'a> MakeWriter<'a> for RollingFile {
+    type Writer = RollingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RollingWriter(self)
+    }
+}
+
+pub struct RollingWriter<'a>(&'a RollingFile);
+
+impl Write for RollingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.0.state.lock().unwrap().file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the log `name` in `dir` followed by its rotated files, newest first.
+pub fn files(dir: &Path, name: &str) -> Vec<PathBuf> {
+    let current = dir.join(name);
+    let mut files: Vec<PathBuf> = current.exists().then_some(current).into_iter().collect();
+    files.extend(rotated(dir, name).into_iter().map(|(_, path)| path));
+    files
+}
+
+/// Rotated files of the log `name` with their index, sorted by index.
+fn rotated(dir: &Path, name: &str) -> Vec<(usize, PathBuf)> {
+    let prefix = format!("{}.", name);
+    let mut files: Vec<(usize, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            let index = file_name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            Some((index, entry.path()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn day(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECS_PER_DAY)
+        .unwrap_or_default()
+}
+
+fn today() -> u64 {
+    day(SystemTime::now())
+}
+
+#[derive(Clone, Serialize)]
+pub struct LogFile {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Last modification, in seconds since the epoch.
+    pub modified: u64,
+}
+
+/// Path of the daemon log, the one of the settings or the platform default.
+pub async fn daemon_log_path(ctx: &AppContext) -> Option<PathBuf> {
+    match ctx.settings().daemon_log_path {
+        Some(path) => Some(path),
+        None => ctx.platform().log_path(ctx.clone()).await,
+    }
+}
+
+/// Lists the app log files, newest first.
+#[tauri::command]
+pub fn get_log_files(handle: tauri::AppHandle) -> Result<Vec<LogFile>, Error> {
+    tracing::info!("handle get_log_files");
+    let ctx = AppContext::from(handle);
+    let dir = match ctx.app_log_dir() {
+        Some(dir) => dir,
+        None => return Ok(vec![]),
+    };
+
+    let mut logs = vec![];
+    for path in files(&dir, APP_LOG) {
+        let meta = fs::metadata(&path)?;
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        logs.push(LogFile {
+            path,
+            size: meta.len(),
+            modified,
+        });
+    }
+    Ok(logs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK: usize = 700 * 1024;
+
+    fn settings(rotation: Rotation, max_files: usize) -> LogSettings {
+        LogSettings {
+            rotation,
+            max_size_mb: 1,
+            max_files,
+            ..LogSettings::default()
+        }
+    }
+
+    /// File names in `dir` with the first byte of their content.
+    fn contents(dir: &Path) -> Vec<(String, u8)> {
+        let mut files: Vec<(String, u8)> = fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                (name, fs::read(entry.path()).unwrap()[0])
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn rotate_by_size() {
+        let cases: [(usize, &[(&str, u8)]); 4] = [
+            (
+                2,
+                &[("app.log", b'd'), ("app.log.1", b'c'), ("app.log.2", b'b')],
+            ),
+            (
+                3,
+                &[
+                    ("app.log", b'd'),
+                    ("app.log.1", b'c'),
+                    ("app.log.2", b'b'),
+                    ("app.log.3", b'a'),
+                ],
+            ),
+            (1, &[("app.log", b'd'), ("app.log.1", b'c')]),
+            (0, &[("app.log", b'd')]),
+        ];
+        for (max_files, expected) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let log = RollingFile::new(dir.path(), APP_LOG);
+            let config = settings(Rotation::Size, max_files);
+            for byte in [b'a', b'b', b'c', b'd'] {
+                // every chunk after the first one exceeds the size limit
+                log.append(&[byte; CHUNK], &config, 0).unwrap();
+            }
+
+            let expected: Vec<(String, u8)> =
+                expected.iter().map(|(n, b)| (n.to_string(), *b)).collect();
+            assert_eq!(contents(dir.path()), expected, "max files {}", max_files);
+            assert_eq!(files(dir.path(), APP_LOG).len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn rotate_below_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = RollingFile::new(dir.path(), APP_LOG);
+        let config = settings(Rotation::Size, 2);
+        for byte in [b'a', b'b', b'c'] {
+            log.append(&[byte; 1024], &config, 0).unwrap();
+        }
+        assert_eq!(contents(dir.path()), vec![("app.log".to_string(), b'a')]);
+        assert_eq!(
+            fs::metadata(dir.path().join(APP_LOG)).unwrap().len(),
+            3 * 1024
+        );
+    }
+
+    #[test]
+    fn rotate_lowered_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = RollingFile::new(dir.path(), APP_LOG);
+        for byte in [b'a', b'b', b'c', b'd'] {
+            log.append(&[byte; CHUNK], &settings(Rotation::Size, 3), 0)
+                .unwrap();
+        }
+        // the files beyond the new limit are dropped on the next rotation
+        log.append(&[b'e'; CHUNK], &settings(Rotation::Size, 1), 0)
+            .unwrap();
+        assert_eq!(
+            contents(dir.path()),
+            vec![
+                ("app.log".to_string(), b'e'),
+                ("app.log.1".to_string(), b'd')
+            ]
+        );
+    }
+
+    #[test]
+    fn rotate_daily() {
+        let cases = [
+            // (rotation, days of the writes, expected files)
+            (Rotation::Daily, [1, 1, 1], 1),
+            (Rotation::Daily, [1, 2, 2], 2),
+            (Rotation::Daily, [1, 2, 3], 3),
+            (Rotation::Size, [1, 2, 3], 1),
+        ];
+        for (rotation, days, expected) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let log = RollingFile::new(dir.path(), APP_LOG);
+            let config = settings(rotation, 5);
+            for day in days {
+                log.append(b"line\n", &config, day).unwrap();
+            }
+            assert_eq!(
+                files(dir.path(), APP_LOG).len(),
+                expected,
+                "{:?} {:?}",
+                rotation,
+                days
+            );
+        }
+    }
+}
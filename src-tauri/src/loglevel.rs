@@ -0,0 +1,91 @@
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::{reload, EnvFilter};
+
+use crate::error::Error;
+use crate::logfile::LogSettings;
+use crate::settings::SettingsStore;
+
+/// Default directives of the console and file layers.
+pub const DEFAULT_LEVEL: &str = "debug,hyper=info";
+
+/// Overrides the levels of the settings for both layers, e.g. `SONARIC_LOG=trace`.
+pub const LOG_ENV: &str = "SONARIC_LOG";
+
+type Reload = Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>;
+
+static LAYERS: Mutex<Vec<(Target, Reload)>> = Mutex::new(Vec::new());
+
+/// Log layer a level applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Console,
+    File,
+}
+
+/// Returns the filter of a layer, starting with `SONARIC_LOG` if set or `directives`.
+/// Only filters registered with `reloadable` follow the settings.
+pub fn filter<S>(target: Target, directives: &str, reloadable: bool) -> reload::Layer<EnvFilter, S>
+where
+    S: tracing::Subscriber + 'static,
+{
+    let directives = std::env::var(LOG_ENV).unwrap_or(directives.to_string());
+    let (layer, handle) = reload::Layer::new(parse(&directives).unwrap_or_else(|e| {
+        eprintln!("invalid log level {:?}: {}", directives, e);
+        EnvFilter::new(DEFAULT_LEVEL)
+    }));
+    if reloadable {
+        let reload: Reload = Box::new(move |filter| handle.reload(filter));
+        LAYERS.lock().unwrap().push((target, reload));
+    }
+    layer
+}
+
+pub fn parse(directives: &str) -> Result<EnvFilter, Error> {
+    EnvFilter::try_new(directives).map_err(|e| Error::from(anyhow!("{}", e)))
+}
+
+/// Replaces the filter of the `target` layers.
+pub fn reload(target: Target, directives: &str) -> Result<(), Error> {
+    for (layer, reload) in LAYERS.lock().unwrap().iter() {
+        if *layer == target {
+            reload(parse(directives)?).map_err(|e| Error::from(anyhow!("{}", e)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies the levels of the settings, unless `SONARIC_LOG` overrides them.
+pub fn configure(settings: &LogSettings) {
+    if std::env::var_os(LOG_ENV).is_some() {
+        return;
+    }
+    for (target, directives) in [
+        (Target::Console, &settings.console_level),
+        (Target::File, &settings.file_level),
+    ] {
+        if let Err(e) = reload(target, directives) {
+            tracing::warn!("set {:?} log level {:?}: {}", target, directives, e);
+        }
+    }
+}
+
+/// Changes the level of the console or file log and persists it.
+#[tauri::command]
+pub fn set_log_level(
+    store: tauri::State<'_, SettingsStore>,
+    target: Target,
+    level: String,
+) -> Result<(), Error> {
+    tracing::info!("handle set_log_level: {:?} {}", target, level);
+    parse(&level)?;
+    store.update(|s| match target {
+        Target::Console => s.logs.console_level = level.clone(),
+        Target::File => s.logs.file_level = level.clone(),
+    })?;
+    // the settings don't apply while SONARIC_LOG is set, the command does
+    reload(target, &level)
+}
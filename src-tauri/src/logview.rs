@@ -0,0 +1,515 @@
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata};
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use regex::Regex;
+use rev_buf_reader::RevBufReader;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::logfile::{self, APP_LOG};
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+/// Lines read at most for one page, so a rare filter match doesn't read the whole file.
+const MAX_SCANNED_LINES: usize = 20_000;
+/// Bytes read at most per poll of a followed log.
+const MAX_READ: u64 = 1024 * 1024;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    App,
+    Daemon,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LogFilter {
+    /// Minimum level, lines without a level, e.g. stack traces, are kept.
+    pub level: Option<Level>,
+    /// Regex the lines must match.
+    pub pattern: Option<String>,
+}
+
+struct Matcher {
+    level: Option<Level>,
+    pattern: Option<Regex>,
+    level_re: Regex,
+}
+
+impl Matcher {
+    fn new(filter: &LogFilter) -> Result<Self, Error> {
+        let pattern = match filter.pattern.as_deref() {
+            Some(p) if !p.is_empty() => {
+                Some(Regex::new(p).map_err(|e| anyhow!("Invalid filter: {}", e))?)
+            }
+            _ => None,
+        };
+        Ok(Self {
+            level: filter.level,
+            pattern,
+            level_re: Regex::new(r"(?i)\b(trace|debug|info|warn|warning|error|fatal|panic)\b")
+                .unwrap(),
+        })
+    }
+
+    /// Level of the line, the first level word, e.g. ` INFO ` or `level=info`.
+    fn level(&self, line: &str) -> Option<Level> {
+        let word = self.level_re.find(line)?.as_str().to_lowercase();
+        Some(match word.as_str() {
+            "trace" => Level::Trace,
+            "debug" => Level::Debug,
+            "info" => Level::Info,
+            "warn" | "warning" => Level::Warn,
+            _ => Level::Error,
+        })
+    }
+
+    fn matches(&self, line: &str) -> bool {
+        if let Some(min) = self.level {
+            if self.level(line).is_some_and(|level| level < min) {
+                return false;
+            }
+        }
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(line),
+            None => true,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct LogPage {
+    pub path: PathBuf,
+    /// Oldest first.
+    pub lines: Vec<String>,
+    /// Offset to pass as `before` to load earlier lines, `None` at the start of the file.
+    pub before: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+struct LogLines {
+    id: u32,
+    source: LogSource,
+    lines: Vec<String>,
+    /// The file was rotated or truncated, the lines start a new file.
+    reset: bool,
+}
+
+/// Managed state of the followed logs.
+#[derive(Default)]
+pub struct LogFollowers {
+    next_id: AtomicU32,
+    // stop flags by follower id, with the label of the window following
+    stop: Mutex<HashMap<u32, (String, Arc<AtomicBool>)>>,
+}
+
+impl LogFollowers {
+    /// Stops the followers of the window, when it's destroyed or its page is reloaded.
+    pub fn unfollow_window(&self, label: &str) {
+        self.stop.lock().unwrap().retain(|id, (window, stop)| {
+            if window != label {
+                return true;
+            }
+            tracing::info!("unfollow log {} of window {}", id, label);
+            stop.store(true, Ordering::Relaxed);
+            false
+        });
+    }
+}
+
+async fn log_path(ctx: &AppContext, source: LogSource) -> Result<PathBuf, Error> {
+    let path = match source {
+        LogSource::App => ctx.app_log_dir().map(|dir| dir.join(APP_LOG)),
+        LogSource::Daemon => logfile::daemon_log_path(ctx).await,
+    };
+    path.ok_or(Error::from(anyhow!(
+        "The {:?} log is not available",
+        source
+    )))
+}
+
+/// Reads a page of lines ending at the `before` offset, the end of the file by default.
+#[tauri::command]
+pub async fn read_log(
+    handle: tauri::AppHandle,
+    source: LogSource,
+    before: Option<u64>,
+    limit: Option<usize>,
+    filter: Option<LogFilter>,
+) -> Result<LogPage, Error> {
+    tracing::info!("handle read_log: {:?} before {:?}", source, before);
+    let path = log_path(&handle.into(), source).await?;
+    let matcher = Matcher::new(&filter.unwrap_or_default())?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    tokio::task::spawn_blocking(move || read_page(path, before, limit, &matcher))
+        .await
+        .expect("should read log")
+}
+
+fn read_page(
+    path: PathBuf,
+    before: Option<u64>,
+    limit: usize,
+    matcher: &Matcher,
+) -> Result<LogPage, Error> {
+    let file = File::open(&path)?;
+    let len = file.metadata()?.len();
+    let end = before.unwrap_or(len).min(len);
+    let mut reader = RevBufReader::new(Bounded { inner: file, end });
+
+    let mut lines = vec![];
+    let mut offset = end;
+    let mut buf = vec![];
+    for _ in 0..MAX_SCANNED_LINES {
+        if lines.len() >= limit {
+            break;
+        }
+        buf.clear();
+        let n = reader.read_until(b'\n', &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        offset -= n as u64;
+
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_matches(|c| c == '\n' || c == '\r');
+        if !line.is_empty() && matcher.matches(line) {
+            lines.push(line.to_string());
+        }
+    }
+    lines.reverse();
+
+    Ok(LogPage {
+        path,
+        lines,
+        before: (offset > 0).then_some(offset),
+    })
+}
+
+/// Emits the lines appended to the log as `log-lines` events to the window until
+/// `unfollow_log` or until the window is gone. Returns the id of the follower.
+#[tauri::command]
+pub async fn follow_log(
+    window: tauri::Window,
+    source: LogSource,
+    filter: Option<LogFilter>,
+) -> Result<u32, Error> {
+    let path = log_path(&window.app_handle().into(), source).await?;
+    let matcher = Matcher::new(&filter.unwrap_or_default())?;
+
+    let followers = window.state::<LogFollowers>();
+    let id = followers.next_id.fetch_add(1, Ordering::Relaxed);
+    let stop = Arc::new(AtomicBool::new(false));
+    followers
+        .stop
+        .lock()
+        .unwrap()
+        .insert(id, (window.label().to_string(), stop.clone()));
+    tracing::info!("handle follow_log: {} {:?} {:?}", id, source, path);
+
+    std::thread::spawn(move || {
+        let mut follower = Follower::new(path);
+        while !stop.load(Ordering::Relaxed) {
+            match follower.poll() {
+                Ok((lines, reset)) => {
+                    let lines: Vec<String> = lines
+                        .into_iter()
+                        .filter(|line| matcher.matches(line))
+                        .collect();
+                    if !lines.is_empty() || reset {
+                        let payload = LogLines {
+                            id,
+                            source,
+                            lines,
+                            reset,
+                        };
+                        if let Err(e) = window.emit("log-lines", payload) {
+                            tracing::warn!("emit log lines: {}", e);
+                        }
+                    }
+                }
+                Err(e) => tracing::trace!("follow {:?}: {}", follower.path, e),
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+        tracing::info!("stopped following {:?}", follower.path);
+    });
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn unfollow_log(followers: tauri::State<'_, LogFollowers>, id: u32) {
+    tracing::info!("handle unfollow_log: {}", id);
+    if let Some((_, stop)) = followers.stop.lock().unwrap().remove(&id) {
+        stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Reads the lines appended to a log, reopening it when it's rotated or truncated.
+struct Follower {
+    path: PathBuf,
+    file: Option<File>,
+    pos: u64,
+    // incomplete last line, completed by the next poll
+    partial: Vec<u8>,
+    started: bool,
+}
+
+impl Follower {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: None,
+            pos: 0,
+            partial: vec![],
+            started: false,
+        }
+    }
+
+    /// Returns the new complete lines and whether the file was replaced.
+    fn poll(&mut self) -> std::io::Result<(Vec<String>, bool)> {
+        let mut reset = false;
+        if self.file.is_none() {
+            let file = File::open(&self.path)?;
+            // lines before following are read with `read_log`
+            self.pos = if self.started {
+                reset = true;
+                0
+            } else {
+                file.metadata()?.len()
+            };
+            self.started = true;
+            self.file = Some(file);
+        }
+
+        // drain the open file first, it may be the rotated one
+        let mut lines = self.read_new()?;
+
+        let current = fs::metadata(&self.path);
+        let opened = self.file.as_ref().map(File::metadata).transpose()?;
+        match (current, opened) {
+            (Ok(current), Some(opened)) if !same_file(&current, &opened) => {
+                self.file = None;
+                self.partial.clear();
+                if let Ok((more, _)) = self.poll() {
+                    lines.extend(more);
+                }
+                reset = true;
+            }
+            (Ok(current), _) if current.len() < self.pos => {
+                self.pos = 0;
+                self.partial.clear();
+                lines.extend(self.read_new()?);
+                reset = true;
+            }
+            // rotated and not recreated yet
+            (Err(_), _) => self.file = None,
+            _ => {}
+        }
+        Ok((lines, reset))
+    }
+
+    fn read_new(&mut self) -> std::io::Result<Vec<String>> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => return Ok(vec![]),
+        };
+        file.seek(SeekFrom::Start(self.pos))?;
+        let mut buf = vec![];
+        let n = file.take(MAX_READ).read_to_end(&mut buf)?;
+        self.pos += n as u64;
+
+        self.partial.extend_from_slice(&buf);
+        let complete = match self.partial.iter().rposition(|b| *b == b'\n') {
+            Some(i) => self.partial.drain(..=i).collect::<Vec<u8>>(),
+            None => return Ok(vec![]),
+        };
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    // no stable file id, a recreated file has a new creation time
+    match (a.created(), b.created()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => true,
+    }
+}
+
+/// Ends a seekable reader at `end`, so the reverse reader starts from there.
+struct Bounded<R> {
+    inner: R,
+    end: u64,
+}
+
+impl<R: Read + Seek> Read for Bounded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let pos = self.inner.stream_position()?;
+        let left = self.end.saturating_sub(pos) as usize;
+        let len = buf.len().min(left);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl<R: Seek> Seek for Bounded<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match pos {
+            SeekFrom::End(offset) => {
+                let target = self.end as i64 + offset;
+                if target < 0 {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "seek before the start",
+                    ));
+                }
+                self.inner.seek(SeekFrom::Start(target as u64))
+            }
+            pos => self.inner.seek(pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::Path;
+
+    fn page(path: &Path, before: Option<u64>, limit: usize) -> (Vec<String>, Option<u64>) {
+        let matcher = Matcher::new(&LogFilter::default()).unwrap();
+        let page = read_page(path.to_path_buf(), before, limit, &matcher).unwrap();
+        (page.lines, page.before)
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn read_pages() {
+        let long = "x".repeat(100_000);
+        let cases = [
+            // (content, before, limit, lines, before of the page)
+            ("a\nb\nc\n", None, 2, vec!["b", "c"], Some(2)),
+            ("a\nb\nc\n", Some(2), 2, vec!["a"], None),
+            ("a\nb\nc\n", None, 10, vec!["a", "b", "c"], None),
+            ("a\r\n\nb", None, 10, vec!["a", "b"], None),
+            ("a\nb\nc\n", Some(100), 1, vec!["c"], Some(4)),
+            ("", None, 10, vec![], None),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        for (content, before, limit, lines, next) in cases {
+            fs::write(&path, content).unwrap();
+            assert_eq!(
+                page(&path, before, limit),
+                (lines.iter().map(|l| l.to_string()).collect(), next),
+                "{:?} before {:?}",
+                content,
+                before
+            );
+        }
+
+        // longer than the buffer of the reverse reader
+        fs::write(&path, format!("first\n{}\nlast\n", long)).unwrap();
+        let (lines, before) = page(&path, None, 2);
+        assert_eq!(lines, vec![long.clone(), "last".to_string()]);
+        assert_eq!(page(&path, before, 2), (vec!["first".to_string()], None));
+    }
+
+    #[test]
+    fn follow_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "before\n").unwrap();
+
+        let mut follower = Follower::new(path.clone());
+        assert_eq!(follower.poll().unwrap(), (vec![], false));
+        append(&path, "one\ntw");
+        assert_eq!(follower.poll().unwrap(), (vec!["one".to_string()], false));
+        append(&path, "o\n");
+        assert_eq!(follower.poll().unwrap(), (vec!["two".to_string()], false));
+
+        // longer than a single read
+        let long = "x".repeat(MAX_READ as usize + 10);
+        append(&path, &format!("{}\n", long));
+        assert_eq!(follower.poll().unwrap(), (vec![], false));
+        assert_eq!(follower.poll().unwrap(), (vec![long], false));
+    }
+
+    #[test]
+    fn follow_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "one\ntwo\n").unwrap();
+
+        let mut follower = Follower::new(path.clone());
+        follower.poll().unwrap();
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(follower.poll().unwrap(), (vec!["new".to_string()], true));
+        append(&path, "next\n");
+        assert_eq!(follower.poll().unwrap(), (vec!["next".to_string()], false));
+    }
+
+    #[test]
+    fn follow_rotated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "one\n").unwrap();
+
+        let mut follower = Follower::new(path.clone());
+        follower.poll().unwrap();
+        // the lines written before the rotation are drained from the old file
+        append(&path, "old\n");
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(
+            follower.poll().unwrap(),
+            (vec!["old".to_string(), "new".to_string()], true)
+        );
+
+        // removed and recreated later
+        fs::rename(&path, dir.path().join("app.log.2")).unwrap();
+        assert_eq!(follower.poll().unwrap(), (vec![], false));
+        assert!(follower.poll().is_err());
+        fs::write(&path, "again\n").unwrap();
+        assert_eq!(follower.poll().unwrap(), (vec!["again".to_string()], true));
+    }
+}
@@ -1,27 +1,61 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod bundle;
+mod cli;
+mod context;
+mod diagnostics;
+mod download;
 mod error;
+mod health;
 mod helper;
+mod logfile;
+mod loglevel;
+mod logview;
+mod operation;
+mod platform;
+mod progress;
+mod redact;
+mod registry;
+mod rollback;
+mod runner;
+mod settings;
+mod state;
+mod telemetry;
+mod tray;
+mod update_policy;
 mod version;
+mod version_cache;
 
-use std::env;
 use std::fs::File;
 use std::io::BufRead;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::anyhow;
-use tauri::api::dialog::blocking::MessageDialogBuilder;
-use tauri::api::dialog::MessageDialogButtons;
-use tauri::api::dialog::MessageDialogKind;
-use tauri::{CustomMenuItem, Env, Manager, Menu, MenuItem, Submenu};
-
-use crate::helper::{
-    copy_and_exec, exec_cmd_bash_script, exec_cmd_script, exec_script, is_wsl_running, try_url,
+use tauri::{CustomMenuItem, Env, Manager, Menu, MenuItem, Submenu, WindowEvent};
+
+use crate::bundle::Bundle;
+use crate::cli::Cli;
+use crate::context::{AppContext, Headless};
+use crate::health::{get_node_health, set_health_interval, HealthMonitor};
+use crate::helper::try_url;
+use crate::logfile::get_log_files;
+use crate::loglevel::{set_log_level, Target};
+use crate::logview::{follow_log, read_log, unfollow_log, LogFollowers};
+use crate::operation::{cancel_operation, get_operations, OperationKind, Operations};
+use crate::platform::{platform, Platform};
+use crate::rollback::rollback_daemon;
+use crate::settings::{
+    get_channel, get_settings, set_channel, set_settings, settings, Consent, Settings,
+    SettingsStore,
 };
-use crate::version::{get_latest_version, parse_version, show_version};
+use crate::state::NodeState;
+use crate::telemetry::{get_telemetry, set_telemetry};
+use crate::update_policy::skip_update;
+use crate::version::{refresh_versions, show_version, version_info};
+use crate::version_cache::{Lookup, VersionCache};
 use error::Error;
-use regex::Regex;
 use reqwest::header::{HeaderMap, HeaderValue};
 use rev_buf_reader::RevBufReader;
 use sentry::protocol::{Attachment, AttachmentType};
@@ -29,172 +63,67 @@ use sentry::Scope;
 use tauri::api::dialog;
 use tauri::api::path::{resolve_path, BaseDirectory};
 use tauri::async_runtime::block_on;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::EnvFilter;
-
-const SENTRY_DSN: &'static str = match option_env!("SENTRY_DSN") {
-    Some(v) => v,
-    None => "https://763b074178d15f7cbc24cad779fd00ac@o4504610754265088.ingest.us.sentry.io/4507299346120704",
-};
 
 #[tauri::command]
-async fn check_install(handle: tauri::AppHandle) -> Result<String, Error> {
+async fn check_install(handle: tauri::AppHandle) -> Result<NodeState, Error> {
     tracing::info!("handle check_install, url: {}", handle.get_window("main").unwrap().url());
 
     let menu = handle.get_window("main").unwrap().menu_handle();
+    menu.get_item("start").set_enabled(false)?;
     menu.get_item("stop").set_enabled(false)?;
     menu.get_item("uninstall").set_enabled(false)?;
     handle.emit_all("status", String::from("Checking components..."))?;
 
-    match env::consts::OS {
-        "macos" | "linux" => {
-            let binary_path = match find_sonaric_binary() {
-                Some(p) => p,
-                None => return Ok("install".to_string()),
-            };
-            let binary_path_str = binary_path.to_str().ok_or(anyhow!("Invalid binary path"))?;
-            tracing::info!("Sonaric binary found: {}", binary_path_str);
-            menu.get_item("uninstall").set_enabled(true)?;
-
-            match exec_script(
-                handle.clone(),
-                binary_path_str,
-                vec!["version"],
-                false,
-                false,
-            )
-            .await
-            {
-                Ok(res) => {
-                    if res.contains("version") {
-                        // sonaric is installed, check version
-                        let version = parse_version(res.clone())?;
-                        let latest_version = get_latest_version().await?;
-                        if latest_version.gt(&version) {
-                            tracing::info!("Update available: {} -> {}", version, latest_version);
-                            return Ok("update".to_string());
-                        }
-                    }
-
-                    if res.contains("daemon is not running") {
-                        // sonaric is installed but not running
-                        return Ok("start".to_string());
-                    }
-                }
-                Err(e) => return Err(Error::from(e)),
-            }
-        }
-        "windows" => {
-            let res = exec_cmd_script(vec!["/C", "wsl", "--version"]).await?;
-            if !res.success {
-                tracing::debug!("WSL is not installed");
-                return Ok("install".to_string());
-            }
-
-            let re = Regex::new(r"^.+ 2\..+$").unwrap();
-            let wsl2 = match res.stdout.lines().next() {
-                Some(line) => re.is_match(line),
-                None => false,
-            };
-            if !wsl2 {
-                tracing::debug!("WSL2 is not installed {}", res.stdout);
-                return Ok("install".to_string());
-            }
-
-            let res = exec_cmd_script(vec!["/C", "wsl", "--list"]).await?;
-            if !res.success || !res.stdout.contains("Ubuntu-22.04") {
-                tracing::debug!("Ubuntu-22.04 is not installed");
-                return Ok("install".to_string());
-            }
-
-            let res = exec_cmd_script(vec!["/C", "wsl", "--list", "--running"]).await?;
-            if !res.success || !res.stdout.contains("Ubuntu-22.04") {
-                tracing::debug!("Ubuntu-22.04 is not running");
-                return Ok("start".to_string());
-            }
-
-            menu.get_item("uninstall").set_enabled(true)?;
-
-            let res = exec_cmd_bash_script(vec![
-                "/C",
-                "wsl",
-                "--distribution",
-                "Ubuntu-22.04",
-                "--user",
-                "root",
-                "--exec",
-                "/bin/bash",
-                "-c",
-                "sonaric version",
-            ])
-            .await?;
-            if res.stderr.contains("daemon is not running") {
-                tracing::debug!("Sonaric daemon is not running");
-                return Ok("start".to_string());
-            }
-            if !res.stdout.contains("version") {
-                tracing::debug!("Sonaic is not installed");
-                return Ok("install".to_string());
-            }
-            let version = parse_version(res.stdout.clone())?;
-            let latest_version = get_latest_version().await?;
-            if latest_version.gt(&version) {
-                tracing::info!("Update available: {} -> {}", version, latest_version);
-                return Ok("update".to_string());
-            }
-        }
-        _ => return Err(Error::from(anyhow!("Unsupported OS"))),
+    let state = platform(&handle).detect(handle.clone().into()).await?;
+    if matches!(state, NodeState::Install { .. }) {
+        return Ok(state);
     }
+    menu.get_item("uninstall").set_enabled(true)?;
 
-    Ok(check_gui(handle).await.unwrap_or_else(|e| {
-        tracing::warn!("{}", e);
-        "start".to_string()
-    }))
-}
-
-fn find_sonaric_binary() -> Option<PathBuf> {
-    let p = env::var_os("PATH").and_then(|paths| {
-        env::split_paths(&paths)
-            .filter_map(|dir| {
-                let full_path = dir.join("sonaric");
-                if full_path.is_file() {
-                    Some(full_path)
-                } else {
-                    None
-                }
-            })
-            .next()
-    });
-
-    if p == None {
-        let paths = vec![
-            "/usr/local/bin/sonaric",
-            "/usr/bin/sonaric",
-            "/opt/homebrew/bin/sonaric",
-        ];
-        for path in paths {
-            if PathBuf::from(path).is_file() {
-                return Some(PathBuf::from(path));
-            }
-        }
+    if !matches!(state, NodeState::Ready { .. }) {
+        menu.get_item("start")
+            .set_enabled(matches!(state, NodeState::Start { .. }))?;
+        return Ok(state);
     }
 
-    p
+    let gui = match check_gui(handle).await {
+        Ok(_) => true,
+        Err(e) => {
+            tracing::warn!("{}", e);
+            false
+        }
+    };
+    let state = state.with_gui(gui);
+    menu.get_item("start")
+        .set_enabled(matches!(state, NodeState::Start { .. }))?;
+    Ok(state)
 }
 
 #[tauri::command]
-async fn check_gui(handle: tauri::AppHandle) -> Result<String, Error> {
+async fn check_gui(handle: tauri::AppHandle) -> Result<NodeState, Error> {
     tracing::info!("handle check_gui");
 
     let menu = handle.get_window("main").unwrap().menu_handle();
     handle.emit_all("status", String::from("Checking GUI..."))?;
 
-    match try_url("http://localhost:44004").await {
+    probe_gui(&settings(&handle)).await?;
+    menu.get_item("uninstall").set_enabled(true)?;
+    menu.get_item("stop").set_enabled(true)?;
+    Ok(NodeState::Ready {
+        backend: platform(&handle).kind(),
+        installed: None,
+        latest: None,
+    })
+}
+
+/// Checks that the GUI served by the daemon responds.
+pub(crate) async fn probe_gui(settings: &Settings) -> Result<(), Error> {
+    match try_url(&settings.gui_url()).await {
         Ok(body) => {
             if body.contains("Sonaric") {
-                menu.get_item("uninstall").set_enabled(true)?;
-                menu.get_item("stop").set_enabled(true)?;
-                Ok("OK".to_string())
+                Ok(())
             } else {
                 Err(Error::from(anyhow!(format!(
                     "Unexpected response: {}",
@@ -212,307 +141,176 @@ async fn check_gui(handle: tauri::AppHandle) -> Result<String, Error> {
 #[tauri::command]
 async fn install_deps(handle: tauri::AppHandle) -> Result<String, Error> {
     tracing::info!("handle install_deps");
-    handle.emit_all("status", String::from("Installing dependencies..."))?;
-
-    match env::consts::OS {
-        "macos" => install_deps_mac(handle).await,
-        "windows" => install_deps_win(handle).await,
-        "linux" => install_deps_linux(handle).await,
-        _ => Err(Error::from(anyhow!("Unsupported OS"))),
-    }
+    install(handle.into()).await
 }
 
-async fn install_deps_win(handle: tauri::AppHandle) -> Result<String, Error> {
-    let res = exec_cmd_script(vec!["/C", "wsl", "--version"]).await?;
-    if !res.success {
-        tracing::debug!("WSL is not installed");
-        return Err(Error::from(anyhow!("It looks like WSL is not installed. Please install WSL from Microsoft Store (https://aka.ms/wslstorepage) and try again.")));
-    }
+pub(crate) async fn install(ctx: AppContext) -> Result<String, Error> {
+    operation::run(&ctx, OperationKind::Install, async {
+        ctx.emit("status", String::from("Installing dependencies..."))?;
 
-    let re = Regex::new(r"^.+ 2\..+$").unwrap();
-    let wsl2 = match res.stdout.lines().next() {
-        Some(line) => re.is_match(line),
-        None => false,
-    };
-    if !wsl2 {
-        tracing::debug!("WSL2 is not installed {}", res.stdout);
-        return Err(Error::from(anyhow!("It looks like you are using WSL 1. Please upgrade to WSL 2 (https://aka.ms/wslstorepage) and try again.")));
-    }
-
-    let resource_path = get_resource_path(handle.clone(), "res/install-win.bat")?;
+        let backend = ctx.platform();
+        let package_types = backend.package_types();
+        if ctx.settings().verified_downloads && !package_types.is_empty() {
+            let bundle = download::download_release(&ctx, &package_types).await?;
+            let install = backend.install_bundle(ctx.clone(), &bundle, false);
+            return rollback::recorded(&ctx, Some(&bundle), install).await;
+        }
 
-    exec_script(
-        handle,
-        "cmd",
-        vec!["/C", resource_path.as_str()],
-        true,
-        true,
-    )
+        rollback::recorded(&ctx, None, backend.install(ctx.clone())).await
+    })
     .await
 }
 
-async fn install_deps_linux(handle: tauri::AppHandle) -> Result<String, Error> {
-    let resource_path = get_resource_path(handle.clone(), "res/install-linux.sh")?;
-    let tmp_path = env::temp_dir().join("sonaric-install.sh");
-    let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
-
-    let appimage_path = env::var("APPIMAGE").unwrap_or("".to_string());
-    if !appimage_path.is_empty() {
-        match create_desktop_entry(handle.clone(), appimage_path) {
-            Ok(_) => {}
-            Err(e) => {
-                tracing::warn!("create desktop entry: {}", e);
-            }
-        }
+/// Opens the install page of the frontend for the picked bundle.
+fn open_bundle_page(window: &tauri::Window, path: Option<PathBuf>) {
+    let app = window.app_handle();
+    if let (Some(path), Some(base_url)) = (path, app.try_state::<BaseUrl>()) {
+        let code = format!(
+            "window.location.href = '{}?action=bundle&path=' + encodeURIComponent({})",
+            base_url.0,
+            serde_json::Value::from(path.to_string_lossy()),
+        );
+        tracing::info!("code: {}", code);
+        window.eval(code.as_str()).unwrap();
     }
-
-    copy_and_exec(handle, resource_path.as_str(), tmp_path_str).await
 }
 
-fn create_desktop_entry(handle: tauri::AppHandle, appimage_path: String) -> Result<(), Error> {
-    let full_path = PathBuf::from(format!(
-        "{}/.local/share/applications/sonaric.desktop",
-        env::var("HOME").unwrap_or("".to_string()),
-    ));
-
-    // skip if present or application folder does not exist
-    if full_path.exists() || !full_path.parent().unwrap().exists() {
-        return Ok(());
-    }
-
-    let tmp_icon_res = get_resource_path(handle.clone(), "res/icon.png")?;
-
-    // copy icon to user's home directory
-    let icon_res_path = handle
-        .path_resolver()
-        .app_config_dir()
-        .ok_or(anyhow!("Invalid config path"))?;
-
-    let icon_res_path = icon_res_path.join("icon.png");
-    std::fs::copy(tmp_icon_res.as_str(), icon_res_path.as_os_str())?;
-
-    let icon_res = icon_res_path.to_str().ok_or(anyhow!("Invalid icon path"))?;
-
-    let content = format!(
-        "[Desktop Entry]
-Name=Sonaric
-Exec={}
-Icon={}
-Type=Application
-Categories=",
-        appimage_path, icon_res
-    );
+#[tauri::command]
+async fn install_bundle(handle: tauri::AppHandle, path: String) -> Result<String, Error> {
+    tracing::info!("handle install_bundle: {}", path);
 
-    std::fs::write(full_path, content)?;
+    let ctx = AppContext::from(handle);
+    operation::run(&ctx, OperationKind::Install, async {
+        ctx.emit("status", String::from("Verifying bundle..."))?;
 
-    Ok(())
+        let bundle = tokio::task::spawn_blocking(move || Bundle::open(Path::new(&path)))
+            .await
+            .map_err(|e| anyhow!("open bundle: {}", e))??;
+        ctx.emit(
+            "status",
+            format!(
+                "Installing Sonaric {} from bundle...",
+                bundle.manifest.version
+            ),
+        )?;
+
+        let backend = ctx.platform();
+        let install = backend.install_bundle(ctx.clone(), &bundle, false);
+        rollback::recorded(&ctx, Some(&bundle), install).await
+    })
+    .await
 }
 
-async fn install_deps_mac(handle: tauri::AppHandle) -> Result<String, Error> {
-    let resource_path = get_resource_path(handle.clone(), "res/install-mac.sh")?;
+#[tauri::command]
+async fn start_daemon(handle: tauri::AppHandle) -> Result<String, Error> {
+    tracing::info!("handle start_daemon");
+    start(handle.into()).await
+}
 
-    exec_script(handle, "bash", vec![resource_path.as_str()], true, true).await
+pub(crate) async fn start(ctx: AppContext) -> Result<String, Error> {
+    operation::run(&ctx, OperationKind::Start, async {
+        ctx.emit("status", String::from("Starting..."))?;
+        ctx.platform().start(ctx.clone()).await
+    })
+    .await
 }
 
 #[tauri::command]
 async fn stop_daemon(handle: tauri::AppHandle) -> Result<String, Error> {
     tracing::info!("handle stop_daemon");
-    handle.emit_all("status", String::from("Stopping..."))?;
-
-    match env::consts::OS {
-        "macos" => stop_daemon_mac(handle).await,
-        "windows" => stop_daemon_win(handle).await,
-        "linux" => stop_daemon_linux(handle).await,
-        _ => Err(Error::from(anyhow!("Unsupported OS"))),
-    }
+    stop(handle.into()).await
 }
 
-async fn stop_daemon_mac(handle: tauri::AppHandle) -> Result<String, Error> {
-    let resource_path = get_resource_path(handle.clone(), "res/stop-mac.sh")?;
-
-    exec_script(handle, "bash", vec![resource_path.as_str()], true, true).await
-}
-
-async fn stop_daemon_linux(handle: tauri::AppHandle) -> Result<String, Error> {
-    let resource_path = get_resource_path(handle.clone(), "res/stop-linux.sh")?;
-    let tmp_path = env::temp_dir().join("sonaric-stop.sh");
-    let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
-
-    copy_and_exec(handle, resource_path.as_str(), tmp_path_str).await
-}
-
-async fn stop_daemon_win(handle: tauri::AppHandle) -> Result<String, Error> {
-    let res = exec_cmd_script(vec!["/C", "wsl", "--list", "--running"]).await?;
-    if !res.success || !res.stdout.contains("Ubuntu-22.04") {
-        return Ok("WSL distribution is not running".to_string());
-    }
-
-    let resource_path = get_resource_path(handle.clone(), "res/stop-win.bat")?;
-
-    exec_script(
-        handle,
-        "cmd",
-        vec!["/C", resource_path.as_str()],
-        true,
-        true,
-    )
-    .await?;
-
-    let confirmation = "Terminate Ubuntu-22.04 distribution in WSL?".to_string();
-    let confirmed = MessageDialogBuilder::new("WSL confirmation", confirmation)
-        .kind(MessageDialogKind::Warning)
-        .buttons(MessageDialogButtons::YesNo)
-        .show();
-    if confirmed {
-        let res = exec_cmd_script(vec!["/C", "wsl", "--terminate", "Ubuntu-22.04"]).await?;
-        if !res.success {
-            tracing::error!("{}", res.stderr);
-            return Err(Error::from(anyhow!("Failed to terminate WSL distribution")));
-        }
-    }
-
-    Ok("Successfully stopped".to_string())
+pub(crate) async fn stop(ctx: AppContext) -> Result<String, Error> {
+    operation::run(&ctx, OperationKind::Stop, async {
+        ctx.emit("status", String::from("Stopping..."))?;
+        ctx.platform().stop(ctx.clone()).await
+    })
+    .await
 }
 
 #[tauri::command]
 async fn uninstall_daemon(handle: tauri::AppHandle) -> Result<String, Error> {
     tracing::info!("handle uninstall_daemon");
-    handle.emit_all("status", String::from("Removing dependencies..."))?;
-
-    match env::consts::OS {
-        "macos" => uninstall_daemon_mac(handle).await,
-        "windows" => uninstall_daemon_win(handle).await,
-        "linux" => uninstall_daemon_linux(handle).await,
-        _ => Err(Error::from(anyhow!("Unsupported OS"))),
-    }
-}
-
-async fn uninstall_daemon_mac(handle: tauri::AppHandle) -> Result<String, Error> {
-    let resource_path = get_resource_path(handle.clone(), "res/uninstall-mac.sh")?;
-
-    exec_script(handle, "bash", vec![resource_path.as_str()], true, true).await
-}
-
-async fn uninstall_daemon_linux(handle: tauri::AppHandle) -> Result<String, Error> {
-    let resource_path = get_resource_path(handle.clone(), "res/uninstall-linux.sh")?;
-    let tmp_path = env::temp_dir().join("sonaric-remove.sh");
-    let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
-
-    let appimage_path = env::var("APPIMAGE").unwrap_or("".to_string());
-    let home_path = env::var("HOME").unwrap_or("".to_string());
-    if !appimage_path.is_empty() && !home_path.is_empty() {
-        // remove desktop shortcut
-        let full_path = PathBuf::from(format!(
-            "{}/.local/share/applications/sonaric.desktop",
-            home_path
-        ));
-
-        if full_path.exists() {
-            match std::fs::remove_file(full_path) {
-                Ok(_) => {}
-                Err(e) => {
-                    tracing::warn!("remove desktop entry: {}", e);
-                }
-            }
-        }
-    }
-
-    copy_and_exec(handle, resource_path.as_str(), tmp_path_str).await
+    uninstall(handle.into()).await
 }
 
-async fn uninstall_daemon_win(handle: tauri::AppHandle) -> Result<String, Error> {
-    let res = exec_cmd_script(vec!["/C", "wsl", "--list"]).await?;
-    if !res.success || !res.stdout.contains("Ubuntu-22.04") {
-        return Ok("WSL distribution is not installed".to_string());
-    }
-
-    let resource_path = get_resource_path(handle.clone(), "res/uninstall-win.bat")?;
-
-    exec_script(
-        handle,
-        "cmd",
-        vec!["/C", resource_path.as_str()],
-        true,
-        true,
-    )
-    .await?;
-
-    let confirmation = "Unregister Ubuntu-22.04 distribution from WSL?
-Caution: Once unregistered, all data, settings, and software associated with that distribution will be permanently lost".to_string();
-    let confirmed = MessageDialogBuilder::new("WSL confirmation", confirmation)
-        .kind(MessageDialogKind::Warning)
-        .buttons(MessageDialogButtons::YesNo)
-        .show();
-    if confirmed {
-        let res = exec_cmd_script(vec!["/C", "wsl", "--unregister", "Ubuntu-22.04"]).await?;
-        if !res.success {
-            tracing::error!("{}", res.stderr);
-            return Err(Error::from(anyhow!(
-                "Failed to unregister WSL distribution"
-            )));
-        }
-    }
-    Ok("Successfully uninstalled".to_string())
+pub(crate) async fn uninstall(ctx: AppContext) -> Result<String, Error> {
+    operation::run(&ctx, OperationKind::Uninstall, async {
+        ctx.emit("status", String::from("Removing dependencies..."))?;
+        ctx.platform().uninstall(ctx.clone()).await
+    })
+    .await
 }
 
 struct BaseUrl(String);
 
-fn get_resource_path(handle: tauri::AppHandle, res: &str) -> Result<String, Error> {
-    let resource_path = handle
-        .path_resolver()
-        .resolve_resource(res)
-        .ok_or(anyhow!("Invalid install path"))?;
-
-    #[cfg(windows)]
-    let resource_path = std::path::Path::new(
-        resource_path
-            .as_path()
-            .to_string_lossy()
-            .trim_start_matches(r"\\?\"),
-    )
-    .to_path_buf();
-
-    let resource_path_str = resource_path
-        .as_path()
-        .to_str()
-        .ok_or(anyhow!("Invalid resource path"))?;
-
-    Ok(resource_path_str.to_string())
-}
-
 fn main() {
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => std::process::exit(Cli::usage(&e)),
+    };
     let ctx = tauri::generate_context!();
 
     let log_path = resolve_path(
         ctx.config(),
         ctx.package_info(),
         &Env::default(),
-        "app.log",
+        logfile::APP_LOG,
         Some(BaseDirectory::AppLog),
     )
     .expect("failed to resolve path");
 
     // Register the Sentry tracing layer to capture breadcrumbs, events, and spans:
+    // the CLI keeps stdout for its output and its console level
+    let (console_writer, console_filter) = match cli {
+        Some(_) => (BoxMakeWriter::new(std::io::stderr), "warn"),
+        None => (BoxMakeWriter::new(std::io::stdout), loglevel::DEFAULT_LEVEL),
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::fmt::layer()
                 .compact()
-                .with_filter(EnvFilter::from("debug,hyper=info")),
+                .with_writer(console_writer)
+                .with_filter(loglevel::filter(
+                    Target::Console,
+                    console_filter,
+                    cli.is_none(),
+                )),
         )
         .with(
             tracing_subscriber::fmt::layer()
                 .with_ansi(false)
                 .compact()
-                .with_writer(tracing_appender::rolling::never(
+                .with_writer(redact::RedactingWriter(logfile::RollingFile::new(
                     log_path.parent().unwrap(),
-                    "app.log",
-                ))
-                .with_filter(EnvFilter::from("debug,hyper=info")),
+                    logfile::APP_LOG,
+                )))
+                .with_filter(loglevel::filter(
+                    Target::File,
+                    loglevel::DEFAULT_LEVEL,
+                    true,
+                )),
         )
         .with(sentry_tracing::layer())
         .init();
 
     tracing::info!("log path: {:?}", log_path);
+
+    let settings_path = resolve_path(
+        ctx.config(),
+        ctx.package_info(),
+        &Env::default(),
+        "settings.json",
+        Some(BaseDirectory::AppConfig),
+    )
+    .expect("failed to resolve path");
+    let settings = SettingsStore::load(settings_path);
+    let version_cache = VersionCache::load(
+        tauri::api::path::app_cache_dir(ctx.config())
+            .map(|dir| dir.join(version_cache::CACHE_FILE)),
+    );
+    tracing::info!("release channel: {}", settings.get().channel);
     let release = format!(
         "{} ({})",
         ctx.config()
@@ -525,22 +323,47 @@ fn main() {
     );
     tracing::info!("app version: {}", release);
 
-    let _guard = sentry::init((
-        SENTRY_DSN,
-        sentry::ClientOptions {
-            release: Some(release.into()),
-            ..Default::default()
-        },
+    let guard = sentry::init((
+        settings.get().sentry_dsn,
+        telemetry::client_options(release),
     ));
 
+    let backend = platform::current().expect("unsupported platform");
+    tracing::info!("platform backend: {:?}", backend.kind());
+
+    if let Some(cli) = cli {
+        let ctx = AppContext::Headless(Arc::new(Headless {
+            config: ctx.config().clone(),
+            package_info: ctx.package_info().clone(),
+            platform: backend,
+            settings,
+            operations: Operations::default(),
+            version_cache,
+            on_event: Cli::event_sink(),
+        }));
+        let code = block_on(cli.run(ctx));
+        // flush the events before exiting
+        drop(guard);
+        std::process::exit(code);
+    }
+
     let menu = Menu::new()
         .add_submenu(Submenu::new(
             "Application",
             Menu::new()
                 .add_item(CustomMenuItem::new("reload", "Reload").accelerator("CmdOrCtrl+R"))
                 .add_native_item(MenuItem::Separator)
+                .add_item(CustomMenuItem::new("start", "Start").disabled())
                 .add_item(CustomMenuItem::new("stop", "Stop").disabled())
                 .add_item(CustomMenuItem::new("uninstall", "Uninstall").disabled())
+                .add_item(CustomMenuItem::new(
+                    "install-bundle",
+                    "Install from Bundle...",
+                ))
+                .add_item(CustomMenuItem::new(
+                    "install-bundle-folder",
+                    "Install from Bundle Folder...",
+                ))
                 .add_native_item(MenuItem::Separator)
                 .add_native_item(MenuItem::Quit),
         ))
@@ -548,12 +371,52 @@ fn main() {
             "Help",
             Menu::new()
                 .add_item(CustomMenuItem::new("about", "About"))
-                .add_item(CustomMenuItem::new("docs", "Documentation")),
+                .add_item(CustomMenuItem::new("docs", "Documentation"))
+                .add_item(CustomMenuItem::new(
+                    "export-diagnostics",
+                    "Export Diagnostics...",
+                ))
+                .add_native_item(MenuItem::Separator)
+                .add_item(telemetry_item(settings.get().telemetry)),
         ));
 
+    let _guard = guard;
+
     tauri::Builder::default()
         .menu(menu)
+        .manage(Platform(backend))
+        .manage(HealthMonitor::default())
+        .manage(Operations::default())
+        .manage(LogFollowers::default())
+        .manage(version_cache)
+        .manage(settings)
+        .setup(|app| {
+            health::spawn(app.handle());
+            telemetry::prompt(&app.handle());
+            update_policy::check_app(&app.handle());
+            Ok(())
+        })
+        .system_tray(tray::build())
+        .on_system_tray_event(tray::on_event)
+        .on_window_event(|event| match event.event() {
+            // keep the node controls in the tray when the window is closed
+            WindowEvent::CloseRequested { api, .. } => {
+                if let Err(e) = event.window().hide() {
+                    tracing::warn!("hide window: {}", e);
+                }
+                api.prevent_close();
+            }
+            WindowEvent::Destroyed => event
+                .window()
+                .state::<LogFollowers>()
+                .unfollow_window(event.window().label()),
+            _ => {}
+        })
         .on_page_load(|window, _payload| {
+            // a reloaded page follows the logs again
+            window
+                .state::<LogFollowers>()
+                .unfollow_window(window.label());
             window
                 .app_handle()
                 .manage(BaseUrl(window.url().to_string()));
@@ -561,6 +424,14 @@ fn main() {
         .on_menu_event(|event| {
             tracing::info!("menu event: {:?}", event.menu_item_id());
             match event.menu_item_id() {
+                "start" => {
+                    let app = event.window().app_handle();
+                    if let Some(base_url) = app.try_state::<BaseUrl>() {
+                        let code = format!("window.location.href = '{}?action=start'", base_url.0);
+                        tracing::info!("code: {}", code);
+                        event.window().eval(code.as_str()).unwrap();
+                    }
+                }
                 "stop" => dialog::ask(
                     Some(&event.window().clone()),
                     "Stop Sonaric",
@@ -597,25 +468,57 @@ fn main() {
                         }
                     },
                 ),
+                "install-bundle" => {
+                    let window = event.window().clone();
+                    dialog::FileDialogBuilder::new()
+                        .set_title("Select Sonaric bundle")
+                        .add_filter("Sonaric bundle", &["gz", "tgz"])
+                        .pick_file(move |path| open_bundle_page(&window, path))
+                }
+                // an extracted bundle, e.g. copied from a USB stick
+                "install-bundle-folder" => {
+                    let window = event.window().clone();
+                    dialog::FileDialogBuilder::new()
+                        .set_title("Select Sonaric bundle folder")
+                        .pick_folder(move |path| open_bundle_page(&window, path))
+                }
                 "reload" => event.window().eval("window.location.reload()").unwrap(),
                 "about" => {
-                    let mes  = match block_on(show_version(event.window().app_handle().clone())) {
-                        Ok(res) => format!("The Sonaric AI node can be deployed in one click and automates the deployment and management of any blockchain node.\n\n\
+                    // the latest versions come from the cache, the dialog doesn't wait for the network
+                    let window = event.window().clone();
+                    tauri::async_runtime::spawn(async move {
+                        let res = version_info(window.app_handle().into(), Lookup::CacheOnly).await;
+                        let mes = format!("The Sonaric AI node can be deployed in one click and automates the deployment and management of any blockchain node.\n\n\
+Release channel: {}\n\
 App version: {}\n\
 Daemon version: {}\n\
-GUI version: {}\n", res.app, res.daemon, res.gui),
-                        Err(e) => {
-                            tracing::error!("show version: {}", e);
-                            "The Sonaric AI node can be deployed in one click and automates the deployment and management of any blockchain node.".to_string()
-                        }
-                    };
+GUI version: {}\n", res.channel, res.app, res.daemon, res.gui);
 
-                    dialog::message(
-                        Some(&event.window().clone()),
-                        "Sonaric AI Node",
-                        mes,
-                    )
+                        dialog::message(Some(&window), "Sonaric AI Node", mes)
+                    });
                 },
+                "export-diagnostics" => {
+                    let window = event.window().clone();
+                    dialog::FileDialogBuilder::new()
+                        .set_title("Export diagnostics")
+                        .set_file_name(&diagnostics::default_file_name())
+                        .add_filter("Zip archive", &["zip"])
+                        .save_file(move |path| {
+                            let path = match path {
+                                Some(path) => path,
+                                None => return,
+                            };
+                            tauri::async_runtime::spawn(async move {
+                                let ctx = AppContext::from(window.app_handle());
+                                let mes = match diagnostics::export(ctx, &path).await {
+                                    Ok(()) => format!("Diagnostics saved to {}", path.display()),
+                                    Err(e) => format!("Failed to export diagnostics: {}", e),
+                                };
+                                dialog::message(Some(&window), "Export Diagnostics", mes);
+                            });
+                        })
+                }
+                "telemetry" => telemetry::toggle(&event.window().app_handle()),
                 "docs" => tauri::api::shell::open(
                     &event.window().shell_scope(),
                     "https://docs.sonaric.xyz/".to_string(),
@@ -627,17 +530,46 @@ GUI version: {}\n", res.app, res.daemon, res.gui),
         })
         .invoke_handler(tauri::generate_handler![
             install_deps,
+            install_bundle,
+            start_daemon,
             check_install,
             check_gui,
             stop_daemon,
             uninstall_daemon,
+            rollback_daemon,
             show_version,
+            refresh_versions,
             report_bug,
+            export_diagnostics,
+            get_node_health,
+            set_health_interval,
+            get_channel,
+            set_channel,
+            get_settings,
+            set_settings,
+            get_operations,
+            cancel_operation,
+            get_telemetry,
+            set_telemetry,
+            skip_update,
+            get_log_files,
+            set_log_level,
+            read_log,
+            follow_log,
+            unfollow_log,
         ])
         .run(ctx)
         .expect("error while running tauri application");
 }
 
+fn telemetry_item(consent: Consent) -> CustomMenuItem {
+    let item = CustomMenuItem::new("telemetry", "Send Crash Reports");
+    match consent {
+        Consent::Granted => item.selected(),
+        _ => item,
+    }
+}
+
 #[derive(Clone, serde::Serialize)]
 pub struct FeedbackBody {
     pub name: String,
@@ -651,48 +583,68 @@ async fn report_bug(
     handle: tauri::AppHandle,
     name: String,
     description: String,
-) -> Result<(), Error> {
+) -> Result<BugReport, Error> {
     tracing::info!("handle report_bug");
+    send_bug_report(handle.into(), name, description).await
+}
 
-    let log_path = handle
-        .path_resolver()
-        .app_log_dir()
-        .unwrap_or_default()
-        .join("app.log");
+/// Where the bug report went, Sentry or a local support bundle if telemetry is off.
+#[derive(Clone, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BugReport {
+    Sent,
+    Saved { path: PathBuf },
+}
 
-    let wsl = match env::consts::OS {
-        "windows" => is_wsl_running().await,
-        _ => false,
-    };
+impl std::fmt::Display for BugReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BugReport::Sent => write!(f, "Bug report sent"),
+            BugReport::Saved { path } => write!(
+                f,
+                "Telemetry is off, bug report saved to {}",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Sends the report with the tail of the app and daemon logs to Sentry, or saves
+/// it to the downloads dir if telemetry is off.
+pub(crate) async fn send_bug_report(
+    ctx: AppContext,
+    name: String,
+    description: String,
+) -> Result<BugReport, Error> {
+    if !telemetry::is_enabled() {
+        let dir = tauri::api::path::download_dir()
+            .or_else(|| ctx.app_log_dir())
+            .ok_or(anyhow!("No directory to save the bug report to"))?;
+        let path = dir.join(diagnostics::report_file_name());
+        diagnostics::export_report(ctx, &path, &name, &description).await?;
+        return Ok(BugReport::Saved { path });
+    }
+
+    let log_paths = logfile::files(&ctx.app_log_dir().unwrap_or_default(), logfile::APP_LOG);
+
+    let settings = ctx.settings();
+    let daemon_log_path = logfile::daemon_log_path(&ctx).await;
+    let name = redact::redact(&name);
 
     let handle = tokio::task::spawn_blocking(move || {
         sentry::with_scope(
             |scope| {
-                add_file_attachment(scope, log_path, "app-log.txt".to_string());
-
-                match env::consts::OS {
-                    "macos" | "linux" => add_file_attachment(
-                        scope,
-                        PathBuf::from("/var/lib/sonaricd/log/sonaricd.log"),
-                        "sonaricd-log.txt".to_string(),
-                    ),
-                    "windows" => {
-                        if wsl {
-                            add_file_attachment(
-                                scope,
-                                PathBuf::from("\\\\wsl.localhost\\Ubuntu-22.04\\var\\lib\\sonaricd\\log\\sonaricd.log"),
-                                "sonaricd-log.txt".to_string(),
-                            );
-                        }
-                    }
-                    _ => {}
+                add_file_attachment(scope, &log_paths, "app-log.txt".to_string());
+
+                if let Some(path) = daemon_log_path {
+                    add_file_attachment(scope, &[path], "sonaricd-log.txt".to_string());
                 }
 
                 scope.set_tag("source", "bug-report");
             },
             || -> Result<(), Error> {
                 let uuid = sentry::capture_message(
-                    format!("Bug report by {}\n{}", name, description).as_str(),
+                    &redact::redact(&format!("Bug report by {}\n{}", name, description)),
                     sentry::Level::Warning,
                 );
                 tracing::info!("Bug report captured: {}", uuid);
@@ -701,19 +653,19 @@ async fn report_bug(
                 headers.insert("Content-Type", HeaderValue::from_static("application/json"));
                 headers.insert(
                     "Authorization",
-                    HeaderValue::from_str(format!("DSN {}", SENTRY_DSN).as_str()).unwrap(),
+                    HeaderValue::from_str(format!("DSN {}", settings.sentry_dsn).as_str()).unwrap(),
                 );
 
                 let body = serde_json::to_string(&FeedbackBody {
-                    name: name,
+                    name,
                     email: "none@example.com".to_string(),
-                    comments: description,
+                    comments: redact::redact(&description),
                     event_id: uuid.simple().to_string(),
                 })?;
 
                 let client = reqwest::blocking::Client::new();
                 let response = client
-                    .post("https://sentry.io/api/0/projects/monkos/sonaric-app/user-feedback/")
+                    .post(settings.feedback_url.as_str())
                     .headers(headers)
                     .body(body)
                     .send()?;
@@ -729,17 +681,38 @@ async fn report_bug(
         )
     });
 
-    handle.await.expect("should spawn report")
+    handle.await.expect("should spawn report")?;
+    Ok(BugReport::Sent)
 }
 
-fn add_file_attachment(scope: &mut Scope, path: PathBuf, name: String) {
-    let mut last_lines = File::open(path).map(|f| tail(&f, 500)).unwrap_or_default();
+#[tauri::command]
+async fn export_diagnostics(handle: tauri::AppHandle, path: String) -> Result<String, Error> {
+    tracing::info!("handle export_diagnostics: {}", path);
+    diagnostics::export(handle.into(), Path::new(&path)).await?;
+    Ok(path)
+}
+
+/// Attaches the last lines of the log files, given newest first, e.g. a log and
+/// its rotated files.
+fn add_file_attachment(scope: &mut Scope, paths: &[PathBuf], name: String) {
+    let mut last_lines = vec![];
+    for path in paths {
+        let limit = 500 - last_lines.len();
+        if limit == 0 {
+            break;
+        }
+        last_lines.extend(
+            File::open(path)
+                .map(|f| tail(&f, limit))
+                .unwrap_or_default(),
+        );
+    }
 
     if !last_lines.is_empty() {
         last_lines.reverse();
         scope.add_attachment(Attachment {
             ty: Some(AttachmentType::Attachment),
-            buffer: last_lines.join("\n").into_bytes(),
+            buffer: redact::redact(&last_lines.join("\n")).into_bytes(),
             filename: name,
             content_type: Some("text/plain".to_string()),
         });
@@ -0,0 +1,510 @@
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::context::AppContext;
+use crate::error::Error;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Long-running lifecycle operation started from the frontend or the tray.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    Install,
+    Start,
+    Stop,
+    Uninstall,
+    Rollback,
+}
+
+impl OperationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationKind::Install => "install",
+            OperationKind::Start => "start",
+            OperationKind::Stop => "stop",
+            OperationKind::Uninstall => "uninstall",
+            OperationKind::Rollback => "rollback",
+        }
+    }
+}
+
+impl Display for OperationKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationState {
+    Queued,
+    Running,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct OperationInfo {
+    pub id: u64,
+    pub kind: OperationKind,
+    pub state: OperationState,
+}
+
+#[derive(Clone, Copy, Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Payload of the `operation-finished` event.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct OperationFinished {
+    pub id: u64,
+    pub kind: OperationKind,
+    pub outcome: Outcome,
+    pub error: Option<String>,
+}
+
+/// Cancellation flag of an operation, shared with the commands it spawns.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<TokenInner>);
+
+tokio::task_local! {
+    // token of the operation `run` is polling
+    static CURRENT: CancelToken;
+}
+
+/// Returns the cancellation token of the operation the caller runs in. Commands run
+/// outside of an operation, e.g. the health and status probes, are never cancelled.
+pub fn current_token() -> CancelToken {
+    CURRENT.try_with(CancelToken::clone).unwrap_or_default()
+}
+
+#[derive(Default)]
+struct TokenInner {
+    cancelled: AtomicBool,
+    pids: Mutex<Vec<u32>>,
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Tracks the processes spawned by a command until the returned guard is dropped.
+    /// Processes registered after the cancellation are killed right away.
+    pub(crate) fn register(&self, pids: Vec<u32>) -> ProcessGuard {
+        self.0.pids.lock().unwrap().extend(&pids);
+        if self.is_cancelled() {
+            pids.iter().for_each(|pid| kill_tree(*pid));
+        }
+        ProcessGuard {
+            token: self.clone(),
+            pids,
+        }
+    }
+
+    fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        let pids = self.0.pids.lock().unwrap().clone();
+        pids.into_iter().for_each(kill_tree);
+    }
+}
+
+pub(crate) struct ProcessGuard {
+    token: CancelToken,
+    pids: Vec<u32>,
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        self.token
+            .0
+            .pids
+            .lock()
+            .unwrap()
+            .retain(|pid| !self.pids.contains(pid));
+    }
+}
+
+struct Entry {
+    info: OperationInfo,
+    token: CancelToken,
+}
+
+/// Managed state serializing the lifecycle operations: a request waits until the
+/// operations before it are finished, a second request of a queued or running kind
+/// is rejected with `Error::Busy`.
+#[derive(Default)]
+pub struct Operations {
+    next_id: AtomicU64,
+    lock: Arc<tokio::sync::Mutex<()>>,
+    // running operation first, then the queued ones in order
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl Operations {
+    /// Queues a new operation and waits until it can run.
+    pub async fn begin(&self, ctx: &AppContext, kind: OperationKind) -> Result<Operation, Error> {
+        let mut operation = {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.iter().any(|e| e.info.kind == kind) {
+                return Err(Error::Busy(kind));
+            }
+            if let Some(running) = entries.first() {
+                tracing::info!("{} waits for {}", kind, running.info.kind);
+                ctx.emit(
+                    "status",
+                    format!("Waiting for {} to finish...", running.info.kind),
+                )?;
+            }
+
+            let info = OperationInfo {
+                id: self.next_id.fetch_add(1, Ordering::SeqCst) + 1,
+                kind,
+                state: OperationState::Queued,
+            };
+            let token = CancelToken::default();
+            entries.push(Entry {
+                info: info.clone(),
+                token: token.clone(),
+            });
+            Operation {
+                info,
+                token,
+                entries: self.entries.clone(),
+                _lock: None,
+            }
+        };
+
+        // tokio's mutex is fair, the operations run in the order they were requested
+        operation._lock = Some(self.lock.clone().lock_owned().await);
+        if operation.token.is_cancelled() {
+            // the frontend lists the queued operation until it's finished
+            let result = Err(Error::Cancelled);
+            operation.finish(ctx, &result);
+            return result;
+        }
+
+        operation.info.state = OperationState::Running;
+        if let Some(entry) = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|e| e.info.id == operation.info.id)
+        {
+            entry.info.state = OperationState::Running;
+        }
+        tracing::info!("operation {} started: {}", operation.info.id, kind);
+        ctx.emit("operation-started", operation.info.clone())?;
+        Ok(operation)
+    }
+
+    /// Returns the running and queued operations.
+    pub fn list(&self) -> Vec<OperationInfo> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.info.clone())
+            .collect()
+    }
+
+    /// Cancels a running or queued operation.
+    pub fn cancel(&self, id: u64) -> Result<(), Error> {
+        let token = match self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.info.id == id)
+        {
+            Some(entry) => {
+                tracing::info!("operation {} cancelled: {}", id, entry.info.kind);
+                entry.token.clone()
+            }
+            None => {
+                return Err(Error::from(anyhow::anyhow!(
+                    "Operation {} is not running",
+                    id
+                )))
+            }
+        };
+        token.cancel();
+        Ok(())
+    }
+}
+
+/// Handle of a started operation, the next one starts when it's dropped.
+pub struct Operation {
+    info: OperationInfo,
+    token: CancelToken,
+    entries: Arc<Mutex<Vec<Entry>>>,
+    _lock: Option<tokio::sync::OwnedMutexGuard<()>>,
+}
+
+impl Operation {
+    /// Emits `operation-finished` with the outcome of the operation.
+    pub fn finish<T>(self, ctx: &AppContext, result: &Result<T, Error>) {
+        let outcome = match result {
+            Ok(_) => Outcome::Succeeded,
+            Err(Error::Cancelled) => Outcome::Cancelled,
+            Err(_) => Outcome::Failed,
+        };
+        tracing::info!(
+            "operation {} finished: {} {:?}",
+            self.info.id,
+            self.info.kind,
+            outcome
+        );
+
+        let payload = OperationFinished {
+            id: self.info.id,
+            kind: self.info.kind,
+            outcome,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        if let Err(e) = ctx.emit("operation-finished", payload) {
+            tracing::warn!("emit operation-finished: {}", e);
+        }
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|e| e.info.id != self.info.id);
+    }
+}
+
+/// Runs `f` as a lifecycle operation of the given kind.
+pub async fn run<T>(
+    ctx: &AppContext,
+    kind: OperationKind,
+    f: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let operation = ctx.operations().begin(ctx, kind).await?;
+    let result = CURRENT.scope(operation.token.clone(), f).await;
+    operation.finish(ctx, &result);
+    result
+}
+
+/// Kills the process and its children. Commands are spawned in their own process
+/// group on unix. Elevated groups can't be killed without asking for the password
+/// again, they run to the end while the operation stops waiting for them.
+fn kill_tree(pid: u32) {
+    #[cfg(unix)]
+    let killed = Command::new("kill")
+        .args(["-TERM", "--", &format!("-{}", pid)])
+        .status()
+        .is_ok_and(|s| s.success());
+
+    #[cfg(windows)]
+    let killed = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .is_ok_and(|s| s.success());
+
+    if !killed {
+        tracing::warn!("failed to kill process tree {}", pid);
+    }
+}
+
+#[tauri::command]
+pub fn get_operations(operations: tauri::State<'_, Operations>) -> Vec<OperationInfo> {
+    operations.list()
+}
+
+#[tauri::command]
+pub async fn cancel_operation(
+    operations: tauri::State<'_, Operations>,
+    id: u64,
+) -> Result<(), Error> {
+    tracing::info!("handle cancel_operation: {}", id);
+    operations.cancel(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Headless;
+    use crate::platform::fake::{FakeBackend, FakeState};
+
+    type Events = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    fn context(dir: &tempfile::TempDir) -> (AppContext, Events) {
+        let events = Events::default();
+        let sink = events.clone();
+        let ctx = AppContext::Headless(Arc::new(Headless {
+            on_event: Box::new(move |event, payload| {
+                sink.lock().unwrap().push((event.to_string(), payload));
+            }),
+            ..Headless::test(
+                Arc::new(FakeBackend::new(FakeState::Running, None)),
+                dir.path(),
+            )
+        }));
+        (ctx, events)
+    }
+
+    /// Waits until `count` operations are running or queued.
+    async fn queued(ctx: &AppContext, count: usize) {
+        while ctx.operations().list().len() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn same_kind_is_busy() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, _) = context(&dir);
+        let install = ctx
+            .operations()
+            .begin(&ctx, OperationKind::Install)
+            .await
+            .unwrap();
+
+        let res = run(&ctx, OperationKind::Install, async { Ok(()) }).await;
+        assert!(matches!(res, Err(Error::Busy(OperationKind::Install))));
+        drop(install);
+        assert!(run(&ctx, OperationKind::Install, async { Ok(()) })
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn kinds_run_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, _) = context(&dir);
+        let install = ctx
+            .operations()
+            .begin(&ctx, OperationKind::Install)
+            .await
+            .unwrap();
+
+        let order = Arc::new(Mutex::new(vec![]));
+        let mut tasks = vec![];
+        for (i, kind) in [
+            OperationKind::Stop,
+            OperationKind::Start,
+            OperationKind::Uninstall,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (task_ctx, order) = (ctx.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                run(&task_ctx, kind, async {
+                    order.lock().unwrap().push(kind);
+                    Ok(())
+                })
+                .await
+            }));
+            queued(&ctx, i + 2).await;
+        }
+        let states: Vec<_> = ctx.operations().list().iter().map(|o| o.state).collect();
+        assert_eq!(
+            states,
+            [
+                OperationState::Running,
+                OperationState::Queued,
+                OperationState::Queued,
+                OperationState::Queued
+            ]
+        );
+        assert!(order.lock().unwrap().is_empty());
+
+        drop(install);
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            [
+                OperationKind::Stop,
+                OperationKind::Start,
+                OperationKind::Uninstall
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancel_queued() {
+        let dir = tempfile::tempdir().unwrap();
+        let (ctx, events) = context(&dir);
+        let install = ctx
+            .operations()
+            .begin(&ctx, OperationKind::Install)
+            .await
+            .unwrap();
+
+        let task = {
+            let ctx = ctx.clone();
+            tokio::spawn(async move { run(&ctx, OperationKind::Stop, async { Ok(()) }).await })
+        };
+        queued(&ctx, 2).await;
+        let id = ctx.operations().list()[1].id;
+        ctx.operations().cancel(id).unwrap();
+        drop(install);
+
+        assert!(matches!(task.await.unwrap(), Err(Error::Cancelled)));
+        let finished: Vec<_> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| event == "operation-finished")
+            .map(|(_, payload)| payload.clone())
+            .collect();
+        assert_eq!(
+            finished,
+            [serde_json::json!({
+                "id": id,
+                "kind": "stop",
+                "outcome": "cancelled",
+                "error": "operation cancelled",
+            })]
+        );
+        assert!(ctx.operations().list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn token_is_scoped_to_the_operation() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = Arc::new(FakeBackend::new(FakeState::Running, None));
+        let ctx = AppContext::Headless(Arc::new(Headless::test(backend, dir.path())));
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (cancelled_tx, cancelled_rx) = tokio::sync::oneshot::channel();
+
+        let install = run(&ctx, OperationKind::Install, async {
+            started_tx.send(()).unwrap();
+            cancelled_rx.await.unwrap();
+            Ok(current_token().is_cancelled())
+        });
+        // e.g. a status probe while the install runs
+        let probe = async {
+            started_rx.await.unwrap();
+            let id = ctx.operations().list()[0].id;
+            ctx.operations().cancel(id).unwrap();
+            let cancelled = current_token().is_cancelled();
+            cancelled_tx.send(()).unwrap();
+            cancelled
+        };
+
+        let (install, probe) = tokio::join!(install, probe);
+        assert!(install.unwrap());
+        assert!(!probe);
+        assert!(ctx.operations().list().is_empty());
+    }
+}
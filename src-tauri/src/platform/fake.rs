@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use semver::Version;
+
+use super::PlatformBackend;
+use crate::bundle::Bundle;
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::state::{Backend, Component, NodeState};
+
+/// In-memory backend that never touches the host, enabled with the `fake-backend`
+/// feature and `SONARIC_BACKEND=fake`, and used by the unit tests. The initial state
+/// can be set with `SONARIC_FAKE_STATE` (`missing`, `stopped`, `running`) and
+/// `SONARIC_FAKE_VERSION`.
+pub(crate) struct FakeBackend {
+    state: Mutex<FakeState>,
+    // set by `install_version` and `install_bundle`
+    version: Mutex<Option<Version>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum FakeState {
+    Missing,
+    Stopped,
+    Running,
+}
+
+impl Default for FakeBackend {
+    fn default() -> Self {
+        let state = match std::env::var("SONARIC_FAKE_STATE").as_deref() {
+            Ok("stopped") => FakeState::Stopped,
+            Ok("running") => FakeState::Running,
+            _ => FakeState::Missing,
+        };
+        let version = std::env::var("SONARIC_FAKE_VERSION")
+            .ok()
+            .and_then(|v| Version::parse(v.trim_start_matches('v')).ok());
+        Self::new(state, version)
+    }
+}
+
+impl FakeBackend {
+    pub(crate) fn new(state: FakeState, version: Option<Version>) -> Self {
+        Self {
+            state: Mutex::new(state),
+            version: Mutex::new(version),
+        }
+    }
+
+    fn version(&self) -> Version {
+        self.version
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or(Version::new(0, 0, 0))
+    }
+
+    fn transition(&self, ctx: AppContext, next: FakeState, output: &str) -> Result<String, Error> {
+        *self.state.lock().unwrap() = next;
+        ctx.emit("install-output", output.to_string())?;
+        Ok(output.to_string())
+    }
+}
+
+#[async_trait]
+impl PlatformBackend for FakeBackend {
+    fn kind(&self) -> Backend {
+        Backend::current().unwrap_or(Backend::Linux)
+    }
+
+    async fn detect(&self, ctx: AppContext) -> Result<NodeState, Error> {
+        let version = self.version();
+        let state = *self.state.lock().unwrap();
+        let policy = ctx.settings().updates;
+        Ok(match state {
+            FakeState::Missing => NodeState::install(self.kind(), Component::Sonaric),
+            FakeState::Stopped => NodeState::from_versions(
+                self.kind(),
+                Some(&version),
+                Some(&version),
+                false,
+                &policy,
+            ),
+            FakeState::Running => {
+                NodeState::from_versions(self.kind(), Some(&version), Some(&version), true, &policy)
+            }
+        })
+    }
+
+    async fn install(&self, ctx: AppContext) -> Result<String, Error> {
+        self.transition(ctx, FakeState::Running, "Sonaric installed")
+    }
+
+    async fn install_bundle(
+        &self,
+        ctx: AppContext,
+        bundle: &Bundle,
+        _downgrade: bool,
+    ) -> Result<String, Error> {
+        *self.version.lock().unwrap() = Some(bundle.version()?);
+        self.transition(ctx, FakeState::Running, "Sonaric installed from bundle")
+    }
+
+    async fn install_version(&self, ctx: AppContext, version: &Version) -> Result<String, Error> {
+        *self.version.lock().unwrap() = Some(version.clone());
+        self.transition(
+            ctx,
+            FakeState::Running,
+            &format!("Sonaric {} installed", version),
+        )
+    }
+
+    fn package_types(&self) -> Vec<&'static str> {
+        vec!["deb"]
+    }
+
+    async fn start(&self, ctx: AppContext) -> Result<String, Error> {
+        if *self.state.lock().unwrap() == FakeState::Missing {
+            return Err(Error::from(anyhow!("Sonaric is not installed")));
+        }
+        self.transition(ctx, FakeState::Running, "Sonaric started")
+    }
+
+    async fn stop(&self, ctx: AppContext) -> Result<String, Error> {
+        if *self.state.lock().unwrap() == FakeState::Missing {
+            return Ok("Sonaric is not installed".to_string());
+        }
+        self.transition(ctx, FakeState::Stopped, "Sonaric stopped")
+    }
+
+    async fn uninstall(&self, ctx: AppContext) -> Result<String, Error> {
+        self.transition(ctx, FakeState::Missing, "Sonaric uninstalled")
+    }
+
+    async fn daemon_version(&self, _ctx: AppContext) -> Result<Version, Error> {
+        match *self.state.lock().unwrap() {
+            FakeState::Missing => Err(Error::from(anyhow!("Sonaric is not installed"))),
+            _ => Ok(self.version()),
+        }
+    }
+
+    async fn daemon_running(&self, _ctx: AppContext) -> bool {
+        *self.state.lock().unwrap() == FakeState::Running
+    }
+
+    async fn log_path(&self, _ctx: AppContext) -> Option<PathBuf> {
+        None
+    }
+
+    async fn diagnostics(&self, _ctx: AppContext) -> Vec<(&'static str, String)> {
+        let state = match *self.state.lock().unwrap() {
+            FakeState::Missing => "missing",
+            FakeState::Stopped => "stopped",
+            FakeState::Running => "running",
+        };
+        vec![(
+            "fake",
+            format!("state: {}\nversion: {}\n", state, self.version()),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::context::Headless;
+
+    fn context(backend: FakeBackend, dir: &tempfile::TempDir) -> AppContext {
+        AppContext::Headless(Arc::new(Headless::test(Arc::new(backend), dir.path())))
+    }
+
+    fn kind() -> Backend {
+        Backend::current().unwrap_or(Backend::Linux)
+    }
+
+    #[tokio::test]
+    async fn detect() {
+        let version = Version::new(1, 4, 0);
+        let cases = [
+            (
+                FakeState::Missing,
+                NodeState::install(kind(), Component::Sonaric),
+            ),
+            (
+                FakeState::Stopped,
+                NodeState::Start {
+                    backend: kind(),
+                    installed: Some("1.4.0".to_string()),
+                    daemon: false,
+                    gui: false,
+                },
+            ),
+            (
+                FakeState::Running,
+                NodeState::Ready {
+                    backend: kind(),
+                    installed: Some("1.4.0".to_string()),
+                    latest: Some("1.4.0".to_string()),
+                },
+            ),
+        ];
+        for (state, expected) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = context(FakeBackend::new(state, Some(version.clone())), &dir);
+            let detected = ctx.platform().detect(ctx.clone()).await.unwrap();
+            assert_eq!(detected, expected, "{:?}", state);
+        }
+    }
+
+    #[tokio::test]
+    async fn lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(FakeBackend::new(FakeState::Missing, None), &dir);
+        let backend = ctx.platform();
+
+        assert!(backend.start(ctx.clone()).await.is_err());
+        assert!(backend.daemon_version(ctx.clone()).await.is_err());
+
+        backend.install(ctx.clone()).await.unwrap();
+        assert!(backend.daemon_running(ctx.clone()).await);
+        assert!(matches!(
+            backend.detect(ctx.clone()).await.unwrap(),
+            NodeState::Ready { .. }
+        ));
+
+        backend.stop(ctx.clone()).await.unwrap();
+        assert!(!backend.daemon_running(ctx.clone()).await);
+        assert!(matches!(
+            backend.detect(ctx.clone()).await.unwrap(),
+            NodeState::Start { daemon: false, .. }
+        ));
+
+        backend
+            .install_version(ctx.clone(), &Version::new(1, 3, 2))
+            .await
+            .unwrap();
+        assert_eq!(
+            backend.daemon_version(ctx.clone()).await.unwrap(),
+            Version::new(1, 3, 2)
+        );
+
+        backend.uninstall(ctx.clone()).await.unwrap();
+        assert_eq!(
+            backend.detect(ctx.clone()).await.unwrap(),
+            NodeState::install(kind(), Component::Sonaric)
+        );
+    }
+}
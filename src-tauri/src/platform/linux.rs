@@ -0,0 +1,263 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use semver::Version;
+
+use super::{
+    command_section, daemon_running_unix, daemon_version_unix, detect_unix, PlatformBackend,
+};
+use crate::bundle::Bundle;
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::helper::{copy_and_exec, exec_script, get_resource_path};
+use crate::runner::CommandRunner;
+use crate::state::{Backend, NodeState};
+
+const DAEMON_LOG_PATH: &str = "/var/lib/sonaricd/log/sonaricd.log";
+
+pub(crate) struct LinuxBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl LinuxBackend {
+    pub(crate) fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+}
+
+#[async_trait]
+impl PlatformBackend for LinuxBackend {
+    fn kind(&self) -> Backend {
+        Backend::Linux
+    }
+
+    async fn detect(&self, ctx: AppContext) -> Result<NodeState, Error> {
+        detect_unix(ctx, self.runner.as_ref(), Backend::Linux).await
+    }
+
+    async fn install(&self, ctx: AppContext) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), "res/install-linux.sh")?;
+        let tmp_path = env::temp_dir().join("sonaric-install.sh");
+        let channel = ctx.settings().channel;
+        let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
+
+        let appimage_path = env::var("APPIMAGE").unwrap_or("".to_string());
+        if !appimage_path.is_empty() {
+            match create_desktop_entry(ctx.clone(), appimage_path) {
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!("create desktop entry: {}", e);
+                }
+            }
+        }
+
+        copy_and_exec(
+            ctx,
+            self.runner.as_ref(),
+            resource_path.as_str(),
+            tmp_path_str,
+            vec![channel.as_str()],
+        )
+        .await
+    }
+
+    async fn install_bundle(
+        &self,
+        ctx: AppContext,
+        bundle: &Bundle,
+        downgrade: bool,
+    ) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), "res/install-bundle-linux.sh")?;
+        let tmp_path = env::temp_dir().join("sonaric-install-bundle.sh");
+        let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
+        // only the verified packages are installed, not whatever else is in the directory
+        let packages = self
+            .package_types()
+            .into_iter()
+            .flat_map(|ext| bundle.packages(ext))
+            .collect::<Vec<_>>();
+        if packages.is_empty() {
+            return Err(Error::from(anyhow!(
+                "The bundle has no packages for this distribution"
+            )));
+        }
+        let mut args = vec![];
+        if downgrade {
+            args.push("--downgrade");
+        }
+        for package in &packages {
+            args.push(package.to_str().ok_or(anyhow!("Invalid bundle path"))?);
+        }
+
+        copy_and_exec(
+            ctx,
+            self.runner.as_ref(),
+            resource_path.as_str(),
+            tmp_path_str,
+            args,
+        )
+        .await
+    }
+
+    async fn install_version(&self, ctx: AppContext, version: &Version) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), "res/rollback-linux.sh")?;
+        let tmp_path = env::temp_dir().join("sonaric-rollback.sh");
+        let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
+        let channel = ctx.settings().channel;
+        let version = version.to_string();
+
+        copy_and_exec(
+            ctx,
+            self.runner.as_ref(),
+            resource_path.as_str(),
+            tmp_path_str,
+            vec![channel.as_str(), version.as_str()],
+        )
+        .await
+    }
+
+    fn package_types(&self) -> Vec<&'static str> {
+        if Path::new("/usr/bin/dpkg").exists() {
+            vec!["deb"]
+        } else if Path::new("/usr/bin/rpm").exists() {
+            vec!["rpm"]
+        } else {
+            vec![]
+        }
+    }
+
+    async fn start(&self, ctx: AppContext) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), "res/start-linux.sh")?;
+
+        exec_script(
+            ctx,
+            self.runner.as_ref(),
+            "/usr/bin/sh",
+            vec![resource_path.as_str()],
+            true,
+            true,
+        )
+        .await
+    }
+
+    async fn stop(&self, ctx: AppContext) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), "res/stop-linux.sh")?;
+        let tmp_path = env::temp_dir().join("sonaric-stop.sh");
+        let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
+
+        copy_and_exec(
+            ctx,
+            self.runner.as_ref(),
+            resource_path.as_str(),
+            tmp_path_str,
+            vec![],
+        )
+        .await
+    }
+
+    async fn uninstall(&self, ctx: AppContext) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), "res/uninstall-linux.sh")?;
+        let tmp_path = env::temp_dir().join("sonaric-remove.sh");
+        let tmp_path_str = tmp_path.to_str().ok_or(anyhow!("Invalid temp path"))?;
+
+        let appimage_path = env::var("APPIMAGE").unwrap_or("".to_string());
+        let home_path = env::var("HOME").unwrap_or("".to_string());
+        if !appimage_path.is_empty() && !home_path.is_empty() {
+            // remove desktop shortcut
+            let full_path = PathBuf::from(format!(
+                "{}/.local/share/applications/sonaric.desktop",
+                home_path
+            ));
+
+            if full_path.exists() {
+                match std::fs::remove_file(full_path) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("remove desktop entry: {}", e);
+                    }
+                }
+            }
+        }
+
+        copy_and_exec(
+            ctx,
+            self.runner.as_ref(),
+            resource_path.as_str(),
+            tmp_path_str,
+            vec![],
+        )
+        .await
+    }
+
+    async fn daemon_version(&self, ctx: AppContext) -> Result<Version, Error> {
+        daemon_version_unix(ctx, self.runner.as_ref()).await
+    }
+
+    async fn daemon_running(&self, ctx: AppContext) -> bool {
+        daemon_running_unix(ctx, self.runner.as_ref()).await
+    }
+
+    async fn log_path(&self, _ctx: AppContext) -> Option<PathBuf> {
+        Some(PathBuf::from(DAEMON_LOG_PATH))
+    }
+
+    async fn diagnostics(&self, _ctx: AppContext) -> Vec<(&'static str, String)> {
+        let runner = self.runner.as_ref();
+        vec![
+            (
+                "os-release",
+                std::fs::read_to_string("/etc/os-release")
+                    .unwrap_or_else(|e| format!("failed: {}", e)),
+            ),
+            ("uname", command_section(runner, "uname", &["-a"], false)),
+            (
+                "systemd",
+                command_section(
+                    runner,
+                    "systemctl",
+                    &["status", "sonaricd", "--no-pager", "--lines=50"],
+                    false,
+                ),
+            ),
+        ]
+    }
+}
+
+fn create_desktop_entry(ctx: AppContext, appimage_path: String) -> Result<(), Error> {
+    let full_path = PathBuf::from(format!(
+        "{}/.local/share/applications/sonaric.desktop",
+        env::var("HOME").unwrap_or("".to_string()),
+    ));
+
+    // skip if present or application folder does not exist
+    if full_path.exists() || !full_path.parent().unwrap().exists() {
+        return Ok(());
+    }
+
+    let tmp_icon_res = get_resource_path(ctx.clone(), "res/icon.png")?;
+
+    // copy icon to user's home directory
+    let icon_res_path = ctx.app_config_dir().ok_or(anyhow!("Invalid config path"))?;
+
+    let icon_res_path = icon_res_path.join("icon.png");
+    std::fs::copy(tmp_icon_res.as_str(), icon_res_path.as_os_str())?;
+
+    let icon_res = icon_res_path.to_str().ok_or(anyhow!("Invalid icon path"))?;
+
+    let content = format!(
+        "[Desktop Entry]
+Name=Sonaric
+Exec={}
+Icon={}
+Type=Application
+Categories=",
+        appimage_path, icon_res
+    );
+
+    std::fs::write(full_path, content)?;
+
+    Ok(())
+}
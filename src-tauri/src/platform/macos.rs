@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use semver::Version;
+
+use super::{
+    command_section, daemon_running_unix, daemon_version_unix, detect_unix, PlatformBackend,
+};
+use crate::bundle::Bundle;
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::helper::{exec_script, get_resource_path};
+use crate::runner::CommandRunner;
+use crate::state::{Backend, NodeState};
+
+const DAEMON_LOG_PATH: &str = "/var/lib/sonaricd/log/sonaricd.log";
+
+pub(crate) struct MacosBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl MacosBackend {
+    pub(crate) fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    async fn exec_resource(
+        &self,
+        ctx: AppContext,
+        res: &str,
+        args: Vec<&str>,
+    ) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), res)?;
+
+        let mut bash_args = vec![resource_path.as_str()];
+        bash_args.extend(args);
+        exec_script(ctx, self.runner.as_ref(), "bash", bash_args, true, true).await
+    }
+}
+
+#[async_trait]
+impl PlatformBackend for MacosBackend {
+    fn kind(&self) -> Backend {
+        Backend::Macos
+    }
+
+    async fn detect(&self, ctx: AppContext) -> Result<NodeState, Error> {
+        detect_unix(ctx, self.runner.as_ref(), Backend::Macos).await
+    }
+
+    async fn install(&self, ctx: AppContext) -> Result<String, Error> {
+        let channel = ctx.settings().channel;
+        self.exec_resource(ctx, "res/install-mac.sh", vec![channel.as_str()])
+            .await
+    }
+
+    async fn install_bundle(
+        &self,
+        _ctx: AppContext,
+        _bundle: &Bundle,
+        _downgrade: bool,
+    ) -> Result<String, Error> {
+        // Homebrew has no offline package format the bundle could provide
+        Err(Error::from(anyhow!(
+            "Offline install from a bundle is not supported on macOS"
+        )))
+    }
+
+    async fn install_version(&self, _ctx: AppContext, version: &Version) -> Result<String, Error> {
+        // the taps only have the formula of the latest release
+        Err(Error::from(anyhow!(
+            "Reinstalling Sonaric {} is not supported on macOS",
+            version
+        )))
+    }
+
+    fn package_types(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    async fn start(&self, ctx: AppContext) -> Result<String, Error> {
+        self.exec_resource(ctx, "res/start-mac.sh", vec![]).await
+    }
+
+    async fn stop(&self, ctx: AppContext) -> Result<String, Error> {
+        self.exec_resource(ctx, "res/stop-mac.sh", vec![]).await
+    }
+
+    async fn uninstall(&self, ctx: AppContext) -> Result<String, Error> {
+        self.exec_resource(ctx, "res/uninstall-mac.sh", vec![])
+            .await
+    }
+
+    async fn daemon_version(&self, ctx: AppContext) -> Result<Version, Error> {
+        daemon_version_unix(ctx, self.runner.as_ref()).await
+    }
+
+    async fn daemon_running(&self, ctx: AppContext) -> bool {
+        daemon_running_unix(ctx, self.runner.as_ref()).await
+    }
+
+    async fn log_path(&self, _ctx: AppContext) -> Option<PathBuf> {
+        Some(PathBuf::from(DAEMON_LOG_PATH))
+    }
+
+    async fn diagnostics(&self, _ctx: AppContext) -> Vec<(&'static str, String)> {
+        let runner = self.runner.as_ref();
+        vec![
+            ("sw_vers", command_section(runner, "sw_vers", &[], false)),
+            ("uname", command_section(runner, "uname", &["-a"], false)),
+            (
+                "launchd",
+                command_section(
+                    runner,
+                    "/bin/sh",
+                    &["-c", "launchctl list | grep -i sonaric"],
+                    false,
+                ),
+            ),
+        ]
+    }
+}
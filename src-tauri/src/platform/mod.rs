@@ -0,0 +1,219 @@
+mod linux;
+mod macos;
+mod wsl;
+
+#[cfg(any(test, feature = "fake-backend"))]
+pub(crate) mod fake;
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use semver::Version;
+use tauri::Manager;
+
+use crate::bundle::Bundle;
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::helper::{decode_utf16, exec_script};
+use crate::runner::{self, CommandRunner};
+use crate::state::{Backend, Component, NodeState};
+use crate::version::{parse_version, try_latest_version, VersionReport};
+use crate::version_cache::Lookup;
+
+/// Platform specific lifecycle of the Sonaric node.
+#[async_trait]
+pub(crate) trait PlatformBackend: Send + Sync {
+    fn kind(&self) -> Backend;
+
+    /// Detects which components are installed and whether the daemon is running.
+    /// GUI reachability is checked separately by `check_gui`.
+    async fn detect(&self, ctx: AppContext) -> Result<NodeState, Error>;
+
+    async fn install(&self, ctx: AppContext) -> Result<String, Error>;
+
+    /// Installs Sonaric from a verified offline bundle without network access.
+    /// `downgrade` allows a version older than the installed one, e.g. for a rollback.
+    async fn install_bundle(
+        &self,
+        ctx: AppContext,
+        bundle: &Bundle,
+        downgrade: bool,
+    ) -> Result<String, Error>;
+
+    /// Package types `install_bundle` can install on this host, e.g. `deb`.
+    fn package_types(&self) -> Vec<&'static str>;
+
+    /// Reinstalls a released daemon version from the package repository, e.g. to
+    /// roll back an update.
+    async fn install_version(&self, ctx: AppContext, version: &Version) -> Result<String, Error>;
+
+    async fn start(&self, ctx: AppContext) -> Result<String, Error>;
+
+    async fn stop(&self, ctx: AppContext) -> Result<String, Error>;
+
+    async fn uninstall(&self, ctx: AppContext) -> Result<String, Error>;
+
+    /// Returns the version of the installed Sonaric CLI.
+    async fn daemon_version(&self, ctx: AppContext) -> Result<Version, Error>;
+
+    /// Checks that the CLI can reach the daemon with `sonaric version`.
+    async fn daemon_running(&self, ctx: AppContext) -> bool;
+
+    /// Returns the path of the daemon log if it can be read from the host.
+    async fn log_path(&self, ctx: AppContext) -> Option<PathBuf>;
+
+    /// Collects host information for the diagnostics bundle, as named text sections.
+    async fn diagnostics(&self, ctx: AppContext) -> Vec<(&'static str, String)>;
+}
+
+/// Managed state holding the backend selected at startup.
+pub(crate) struct Platform(pub(crate) Arc<dyn PlatformBackend>);
+
+pub(crate) fn current() -> Result<Arc<dyn PlatformBackend>, Error> {
+    #[cfg(feature = "fake-backend")]
+    if env::var("SONARIC_BACKEND").is_ok_and(|v| v == "fake") {
+        tracing::warn!("using fake platform backend");
+        return Ok(Arc::new(fake::FakeBackend::default()));
+    }
+
+    let runner = runner::current();
+
+    // recorded fixtures can be replayed against the backend of another platform
+    #[cfg(feature = "command-fixtures")]
+    match env::var("SONARIC_BACKEND").as_deref() {
+        Ok("linux") => return Ok(Arc::new(linux::LinuxBackend::new(runner))),
+        Ok("macos") => return Ok(Arc::new(macos::MacosBackend::new(runner))),
+        Ok("wsl") => return Ok(Arc::new(wsl::WslBackend::new(runner))),
+        _ => {}
+    }
+
+    match Backend::current()? {
+        Backend::Linux => Ok(Arc::new(linux::LinuxBackend::new(runner))),
+        Backend::Macos => Ok(Arc::new(macos::MacosBackend::new(runner))),
+        Backend::Wsl => Ok(Arc::new(wsl::WslBackend::new(runner))),
+    }
+}
+
+pub(crate) fn platform(handle: &tauri::AppHandle) -> Arc<dyn PlatformBackend> {
+    handle.state::<Platform>().0.clone()
+}
+
+pub(crate) fn find_sonaric_binary() -> Option<PathBuf> {
+    let p = env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .filter_map(|dir| {
+                let full_path = dir.join("sonaric");
+                if full_path.is_file() {
+                    Some(full_path)
+                } else {
+                    None
+                }
+            })
+            .next()
+    });
+
+    if p.is_none() {
+        let paths = vec![
+            "/usr/local/bin/sonaric",
+            "/usr/bin/sonaric",
+            "/opt/homebrew/bin/sonaric",
+        ];
+        for path in paths {
+            if PathBuf::from(path).is_file() {
+                return Some(PathBuf::from(path));
+            }
+        }
+    }
+
+    p
+}
+
+/// Runs `sonaric version` with the binary found on the host (Linux and macOS).
+async fn exec_sonaric_version(
+    ctx: AppContext,
+    runner: &dyn CommandRunner,
+) -> Result<Option<String>, Error> {
+    let binary_path = match find_sonaric_binary() {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+    let binary_path_str = binary_path.to_str().ok_or(anyhow!("Invalid binary path"))?;
+    tracing::info!("Sonaric binary found: {}", binary_path_str);
+
+    let res = exec_script(ctx, runner, binary_path_str, vec!["version"], false, false).await?;
+    Ok(Some(res))
+}
+
+async fn detect_unix(
+    ctx: AppContext,
+    runner: &dyn CommandRunner,
+    backend: Backend,
+) -> Result<NodeState, Error> {
+    let res = match exec_sonaric_version(ctx.clone(), runner).await? {
+        Some(res) => res,
+        None => return Ok(NodeState::install(backend, Component::Sonaric)),
+    };
+
+    // sonaric is installed, check version
+    let report = if res.contains("version") {
+        Some(VersionReport::parse(&res)?)
+    } else {
+        None
+    };
+    let latest_version = match report {
+        Some(_) => try_latest_version(&ctx, Lookup::Cached).await,
+        None => None,
+    };
+
+    Ok(NodeState::from_versions(
+        backend,
+        report.as_ref().map(VersionReport::installed),
+        latest_version.as_ref(),
+        match &report {
+            Some(report) => report.daemon_running,
+            None => !res.contains("daemon is not running"),
+        },
+        &ctx.settings().updates,
+    ))
+}
+
+async fn daemon_version_unix(
+    ctx: AppContext,
+    runner: &dyn CommandRunner,
+) -> Result<Version, Error> {
+    let res = exec_sonaric_version(ctx, runner)
+        .await?
+        .ok_or(anyhow!("Sonaric binary not found"))?;
+    if !res.contains("version") {
+        return Err(Error::from(anyhow!("Sonaric is not installed")));
+    }
+    parse_version(res)
+}
+
+/// Runs a diagnostic command and returns its output, failures are part of the output.
+fn command_section(runner: &dyn CommandRunner, cmd: &str, args: &[&str], utf16: bool) -> String {
+    let mut section = format!("$ {} {}\n", cmd, args.join(" "));
+    match runner.output(cmd, args) {
+        Ok(output) => {
+            let stdout = match utf16 {
+                true => decode_utf16(&output.stdout).unwrap_or_default(),
+                false => String::from_utf8_lossy(&output.stdout).into_owned(),
+            };
+            section.push_str(&stdout);
+            section.push_str(&String::from_utf8_lossy(&output.stderr));
+            section.push_str(&format!("\nexit code: {:?}\n", output.code));
+        }
+        Err(e) => section.push_str(&format!("failed: {}\n", e)),
+    }
+    section
+}
+
+async fn daemon_running_unix(ctx: AppContext, runner: &dyn CommandRunner) -> bool {
+    match exec_sonaric_version(ctx, runner).await {
+        Ok(Some(res)) => VersionReport::parse(&res).is_ok_and(|r| r.daemon_running),
+        _ => false,
+    }
+}
@@ -0,0 +1,522 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use semver::Version;
+use tauri::api::dialog::blocking::MessageDialogBuilder;
+use tauri::api::dialog::MessageDialogButtons;
+use tauri::api::dialog::MessageDialogKind;
+
+use super::{command_section, PlatformBackend};
+use crate::bundle::Bundle;
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::helper::{
+    exec_cmd_bash_script, exec_cmd_script, exec_script, get_resource_path, ScriptOutput,
+};
+use crate::runner::CommandRunner;
+use crate::state::{Backend, Component, NodeState};
+use crate::version::{parse_version, try_latest_version, VersionReport};
+use crate::version_cache::Lookup;
+
+pub(crate) struct WslBackend {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl WslBackend {
+    pub(crate) fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// Checks that WSL 2 is installed, returns the missing component otherwise.
+    async fn check_wsl(&self) -> Result<Option<Component>, Error> {
+        let res = exec_cmd_script(self.runner.as_ref(), vec!["/C", "wsl", "--version"]).await?;
+        if !res.success {
+            tracing::debug!("WSL is not installed");
+            return Ok(Some(Component::Wsl));
+        }
+
+        if !is_wsl2(&res.stdout) {
+            tracing::debug!("WSL2 is not installed {}", res.stdout);
+            return Ok(Some(Component::Wsl2));
+        }
+
+        Ok(None)
+    }
+
+    async fn is_wsl_running(&self, distro: &str) -> bool {
+        let installed =
+            match exec_cmd_script(self.runner.as_ref(), vec!["/C", "wsl", "--version"]).await {
+                Ok(res) => res.success,
+                _ => false,
+            };
+        if !installed {
+            return false;
+        }
+        match exec_cmd_script(
+            self.runner.as_ref(),
+            vec!["/C", "wsl", "--list", "--running"],
+        )
+        .await
+        {
+            Ok(res) => res.success && res.stdout.contains(distro),
+            _ => false,
+        }
+    }
+
+    async fn exec_sonaric_version(&self, distro: &str) -> Result<ScriptOutput, Error> {
+        exec_cmd_bash_script(
+            self.runner.as_ref(),
+            vec![
+                "/C",
+                "wsl",
+                "--distribution",
+                distro,
+                "--user",
+                "root",
+                "--exec",
+                "/bin/bash",
+                "-c",
+                "sonaric version",
+            ],
+        )
+        .await
+    }
+
+    async fn exec_resource(
+        &self,
+        ctx: AppContext,
+        res: &str,
+        args: Vec<&str>,
+    ) -> Result<String, Error> {
+        let resource_path = get_resource_path(ctx.clone(), res)?;
+
+        let mut cmd_args = vec!["/C", resource_path.as_str()];
+        cmd_args.extend(args);
+        exec_script(ctx, self.runner.as_ref(), "cmd", cmd_args, true, true).await
+    }
+}
+
+#[async_trait]
+impl PlatformBackend for WslBackend {
+    fn kind(&self) -> Backend {
+        Backend::Wsl
+    }
+
+    async fn detect(&self, ctx: AppContext) -> Result<NodeState, Error> {
+        let settings = ctx.settings();
+        let distro = settings.wsl_distribution.as_str();
+        if let Some(missing) = self.check_wsl().await? {
+            return Ok(NodeState::install(Backend::Wsl, missing));
+        }
+
+        let res = exec_cmd_script(self.runner.as_ref(), vec!["/C", "wsl", "--list"]).await?;
+        if !res.success || !res.stdout.contains(distro) {
+            tracing::debug!("{} is not installed", distro);
+            return Ok(NodeState::install(Backend::Wsl, Component::Distribution));
+        }
+
+        let res = exec_cmd_script(
+            self.runner.as_ref(),
+            vec!["/C", "wsl", "--list", "--running"],
+        )
+        .await?;
+        if !res.success || !res.stdout.contains(distro) {
+            tracing::debug!("{} is not running", distro);
+            return Ok(NodeState::from_versions(
+                Backend::Wsl,
+                None,
+                None,
+                false,
+                &settings.updates,
+            ));
+        }
+
+        let res = self.exec_sonaric_version(distro).await?;
+        if !res.stdout.contains("version") {
+            if res.stderr.contains("daemon is not running") {
+                tracing::debug!("Sonaric daemon is not running");
+                return Ok(NodeState::from_versions(
+                    Backend::Wsl,
+                    None,
+                    None,
+                    false,
+                    &settings.updates,
+                ));
+            }
+            tracing::debug!("Sonaic is not installed");
+            return Ok(NodeState::install(Backend::Wsl, Component::Sonaric));
+        }
+        let report = VersionReport::parse(&format!("{}\n{}", res.stdout, res.stderr))?;
+        if !report.daemon_running {
+            tracing::debug!("Sonaric daemon is not running");
+            return Ok(NodeState::from_versions(
+                Backend::Wsl,
+                Some(report.installed()),
+                None,
+                false,
+                &settings.updates,
+            ));
+        }
+        let latest_version = try_latest_version(&ctx, Lookup::Cached).await;
+
+        Ok(NodeState::from_versions(
+            Backend::Wsl,
+            Some(report.installed()),
+            latest_version.as_ref(),
+            true,
+            &settings.updates,
+        ))
+    }
+
+    async fn install(&self, ctx: AppContext) -> Result<String, Error> {
+        match self.check_wsl().await? {
+            Some(Component::Wsl) => return Err(Error::from(anyhow!("It looks like WSL is not installed. Please install WSL from Microsoft Store (https://aka.ms/wslstorepage) and try again."))),
+            Some(_) => return Err(Error::from(anyhow!("It looks like you are using WSL 1. Please upgrade to WSL 2 (https://aka.ms/wslstorepage) and try again."))),
+            None => {}
+        }
+
+        let settings = ctx.settings();
+        self.exec_resource(
+            ctx,
+            "res/install-win.bat",
+            vec![
+                settings.channel.as_str(),
+                settings.wsl_distribution.as_str(),
+            ],
+        )
+        .await
+    }
+
+    // dpkg installs an older version without asking, downgrades need no flag
+    async fn install_bundle(
+        &self,
+        ctx: AppContext,
+        bundle: &Bundle,
+        _downgrade: bool,
+    ) -> Result<String, Error> {
+        if self.check_wsl().await?.is_some() {
+            return Err(Error::from(anyhow!(
+                "WSL 2 is required for an offline install"
+            )));
+        }
+        // only the verified packages are installed, not whatever else is in the directory
+        let packages = bundle
+            .packages("deb")
+            .iter()
+            .filter_map(|p| p.file_name()?.to_str().map(str::to_string))
+            .collect::<Vec<_>>();
+        if packages.is_empty() {
+            return Err(Error::from(anyhow!("The bundle has no .deb packages")));
+        }
+
+        let distro = ctx.settings().wsl_distribution;
+        let bundle_dir = bundle.dir.to_str().ok_or(anyhow!("Invalid bundle path"))?;
+        let mut args = vec![bundle_dir, distro.as_str()];
+        args.extend(packages.iter().map(String::as_str));
+        self.exec_resource(ctx, "res/install-bundle-win.bat", args)
+            .await
+    }
+
+    async fn install_version(&self, ctx: AppContext, version: &Version) -> Result<String, Error> {
+        let settings = ctx.settings();
+        let version = version.to_string();
+        self.exec_resource(
+            ctx,
+            "res/rollback-win.bat",
+            vec![
+                version.as_str(),
+                settings.channel.as_str(),
+                settings.wsl_distribution.as_str(),
+            ],
+        )
+        .await
+    }
+
+    fn package_types(&self) -> Vec<&'static str> {
+        vec!["deb"]
+    }
+
+    async fn start(&self, ctx: AppContext) -> Result<String, Error> {
+        let distro = ctx.settings().wsl_distribution;
+        let res = exec_cmd_script(self.runner.as_ref(), vec!["/C", "wsl", "--list"]).await?;
+        if !res.success || !res.stdout.contains(distro.as_str()) {
+            return Err(Error::from(anyhow!("WSL distribution is not installed")));
+        }
+
+        self.exec_resource(ctx, "res/start-win.bat", vec![distro.as_str()])
+            .await
+    }
+
+    async fn stop(&self, ctx: AppContext) -> Result<String, Error> {
+        let distro = ctx.settings().wsl_distribution;
+        let res = exec_cmd_script(
+            self.runner.as_ref(),
+            vec!["/C", "wsl", "--list", "--running"],
+        )
+        .await?;
+        if !res.success || !res.stdout.contains(distro.as_str()) {
+            return Ok("WSL distribution is not running".to_string());
+        }
+
+        self.exec_resource(ctx, "res/stop-win.bat", vec![distro.as_str()])
+            .await?;
+
+        let confirmation = format!("Terminate {} distribution in WSL?", distro);
+        let confirmed = MessageDialogBuilder::new("WSL confirmation", confirmation)
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::YesNo)
+            .show();
+        if confirmed {
+            let res = exec_cmd_script(
+                self.runner.as_ref(),
+                vec!["/C", "wsl", "--terminate", distro.as_str()],
+            )
+            .await?;
+            if !res.success {
+                tracing::error!("{}", res.stderr);
+                return Err(Error::from(anyhow!("Failed to terminate WSL distribution")));
+            }
+        }
+
+        Ok("Successfully stopped".to_string())
+    }
+
+    async fn uninstall(&self, ctx: AppContext) -> Result<String, Error> {
+        let distro = ctx.settings().wsl_distribution;
+        let res = exec_cmd_script(self.runner.as_ref(), vec!["/C", "wsl", "--list"]).await?;
+        if !res.success || !res.stdout.contains(distro.as_str()) {
+            return Ok("WSL distribution is not installed".to_string());
+        }
+
+        self.exec_resource(ctx, "res/uninstall-win.bat", vec![distro.as_str()])
+            .await?;
+
+        let confirmation = format!("Unregister {} distribution from WSL?
+Caution: Once unregistered, all data, settings, and software associated with that distribution will be permanently lost", distro);
+        let confirmed = MessageDialogBuilder::new("WSL confirmation", confirmation)
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::YesNo)
+            .show();
+        if confirmed {
+            let res = exec_cmd_script(
+                self.runner.as_ref(),
+                vec!["/C", "wsl", "--unregister", distro.as_str()],
+            )
+            .await?;
+            if !res.success {
+                tracing::error!("{}", res.stderr);
+                return Err(Error::from(anyhow!(
+                    "Failed to unregister WSL distribution"
+                )));
+            }
+        }
+        Ok("Successfully uninstalled".to_string())
+    }
+
+    async fn daemon_version(&self, ctx: AppContext) -> Result<Version, Error> {
+        let distro = ctx.settings().wsl_distribution;
+        if !self.is_wsl_running(&distro).await {
+            return Err(Error::from(anyhow!("WSL distribution is not running")));
+        }
+
+        let res = self.exec_sonaric_version(&distro).await?;
+        if !res.stdout.contains("version") {
+            return Err(Error::from(anyhow!("Sonaric is not installed")));
+        }
+        parse_version(res.stdout)
+    }
+
+    async fn daemon_running(&self, ctx: AppContext) -> bool {
+        let distro = ctx.settings().wsl_distribution;
+        if !self.is_wsl_running(&distro).await {
+            return false;
+        }
+
+        match self.exec_sonaric_version(&distro).await {
+            Ok(res) => VersionReport::parse(&format!("{}\n{}", res.stdout, res.stderr))
+                .is_ok_and(|r| r.daemon_running),
+            Err(_) => false,
+        }
+    }
+
+    async fn log_path(&self, ctx: AppContext) -> Option<PathBuf> {
+        let distro = ctx.settings().wsl_distribution;
+        if !self.is_wsl_running(&distro).await {
+            return None;
+        }
+        Some(PathBuf::from(format!(
+            "\\\\wsl.localhost\\{}\\var\\lib\\sonaricd\\log\\sonaricd.log",
+            distro
+        )))
+    }
+
+    async fn diagnostics(&self, ctx: AppContext) -> Vec<(&'static str, String)> {
+        let distro = ctx.settings().wsl_distribution;
+        let runner = self.runner.as_ref();
+        vec![
+            (
+                "windows",
+                command_section(runner, "cmd", &["/C", "ver"], false),
+            ),
+            (
+                "wsl-status",
+                command_section(runner, "cmd", &["/C", "wsl", "--status"], true),
+            ),
+            (
+                "wsl-list",
+                command_section(runner, "cmd", &["/C", "wsl", "--list", "--verbose"], true),
+            ),
+            (
+                "systemd",
+                command_section(
+                    runner,
+                    "cmd",
+                    &[
+                        "/C",
+                        "wsl",
+                        "--distribution",
+                        &distro,
+                        "--user",
+                        "root",
+                        "--exec",
+                        "systemctl",
+                        "status",
+                        "sonaricd",
+                        "--no-pager",
+                        "--lines=50",
+                    ],
+                    false,
+                ),
+            ),
+        ]
+    }
+}
+
+// first line of `wsl --version` of WSL 2
+static WSL2_VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^.+ 2\..+$").unwrap());
+
+/// Checks the first line of `wsl --version`, e.g. "WSL version: 2.1.5.0".
+pub(crate) fn is_wsl2(stdout: &str) -> bool {
+    match stdout.lines().next() {
+        Some(line) => WSL2_VERSION.is_match(line),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::path::Path;
+
+    use super::*;
+    use crate::context::Headless;
+    use crate::runner::replay::ReplayRunner;
+
+    fn context(fixtures: &Path, dir: &tempfile::TempDir) -> AppContext {
+        let runner = Arc::new(ReplayRunner::load(fixtures).unwrap());
+        let backend = Arc::new(WslBackend::new(runner));
+        let ctx = AppContext::Headless(Arc::new(Headless::test(backend, dir.path())));
+
+        // nothing listens there, the latest version is unknown
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        ctx.update_settings(|s| s.releases_url = format!("http://127.0.0.1:{}", port))
+            .unwrap();
+        ctx
+    }
+
+    fn fixture(dir: &Path, name: &str, args: &[&str], stdout: &str, code: i32) {
+        let argv = std::iter::once("cmd").chain(args.iter().copied());
+        let fixture = serde_json::json!({
+            "argv": argv.collect::<Vec<_>>(),
+            "stdout": stdout,
+            "encoding": "utf-16le",
+            "code": code,
+        });
+        std::fs::write(dir.join(format!("{}.json", name)), fixture.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn detect_recorded() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let cases = [
+            (
+                "wsl-running",
+                NodeState::Ready {
+                    backend: Backend::Wsl,
+                    installed: Some("1.1.0".to_string()),
+                    latest: None,
+                },
+            ),
+            (
+                "wsl-daemon-stopped",
+                NodeState::Start {
+                    backend: Backend::Wsl,
+                    installed: Some("1.1.0".to_string()),
+                    daemon: false,
+                    gui: false,
+                },
+            ),
+        ];
+        for (name, expected) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = context(&root.join(name), &dir);
+            let state = ctx.platform().detect(ctx.clone()).await.unwrap();
+            assert_eq!(state, expected, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn detect_missing() {
+        let version = "WSL version: 2.1.5.0\r\nKernel version: 5.15.146.1-2\r\n";
+        let list = "Windows Subsystem for Linux Distributions:\r\nUbuntu-22.04 (Default)\r\n";
+        let cases = [
+            // wsl.exe of WSL 1 doesn't know --version
+            (vec![("", 1)], Component::Wsl),
+            (vec![("WSL version: 1.2.5.0\r\n", 0)], Component::Wsl2),
+            (
+                vec![
+                    (version, 0),
+                    ("Windows Subsystem for Linux Distributions:\r\n", 0),
+                ],
+                Component::Distribution,
+            ),
+            (vec![(version, 0), (list, 1)], Component::Distribution),
+        ];
+        let commands: [&[&str]; 2] = [&["/C", "wsl", "--version"], &["/C", "wsl", "--list"]];
+        for (outputs, missing) in cases {
+            let fixtures = tempfile::tempdir().unwrap();
+            for (i, (stdout, code)) in outputs.iter().enumerate() {
+                fixture(fixtures.path(), &i.to_string(), commands[i], stdout, *code);
+            }
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = context(fixtures.path(), &dir);
+            let state = ctx.platform().detect(ctx.clone()).await.unwrap();
+            assert_eq!(state, NodeState::install(Backend::Wsl, missing));
+        }
+    }
+
+    #[test]
+    fn wsl2() {
+        let cases = [
+            ("WSL version: 2.1.5.0\r\nKernel version: 5.15.146.1-2", true),
+            ("WSL-Version: 2.0.9.0", true),
+            ("WSL version: 1.2.5.0", false),
+            (
+                "Kernel version: 5.15.146.1-2\r\nWSL version: 2.1.5.0",
+                false,
+            ),
+            ("", false),
+        ];
+        for (stdout, expected) in cases {
+            assert_eq!(is_wsl2(stdout), expected, "{:?}", stdout);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+/// Prefix of the progress lines printed by the scripts in `res/`:
+/// `::progress phase=<phase> percent=<0-100>` and `::warning <message>`.
+const PROGRESS_MARKER: &str = "::progress ";
+const WARNING_MARKER: &str = "::warning ";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    DetectDistro,
+    AddRepo,
+    UpdateIndex,
+    InstallPackage,
+    WaitDaemon,
+    PullGui,
+    Done,
+}
+
+impl Phase {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "detect-distro" => Phase::DetectDistro,
+            "add-repo" => Phase::AddRepo,
+            "update-index" => Phase::UpdateIndex,
+            "install-package" => Phase::InstallPackage,
+            "wait-daemon" => Phase::WaitDaemon,
+            "pull-gui" => Phase::PullGui,
+            "done" => Phase::Done,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Progress {
+    Phase { phase: Phase, percent: u8 },
+    Warning { message: String },
+}
+
+/// Turns the output of the install scripts into `install-progress` events.
+/// The percentage never goes backwards, so retried steps don't move the bar back.
+#[derive(Default)]
+pub struct ProgressParser {
+    percent: u8,
+}
+
+impl ProgressParser {
+    /// Returns the progress for a marker or well-known line, `None` for regular output.
+    pub fn feed(&mut self, line: &str) -> Option<Progress> {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix(PROGRESS_MARKER) {
+            return self.parse_phase(rest);
+        }
+        if let Some(message) = line.strip_prefix(WARNING_MARKER) {
+            return Some(Progress::Warning {
+                message: message.trim().to_string(),
+            });
+        }
+
+        // output of the tools the scripts run
+        if let Some(message) = line.strip_prefix("W: ").or(line.strip_prefix("Warning: ")) {
+            return Some(Progress::Warning {
+                message: message.trim().to_string(),
+            });
+        }
+        None
+    }
+
+    /// Returns whether the line belongs to the progress protocol and should be kept out of the log.
+    pub fn is_marker(line: &str) -> bool {
+        let line = line.trim();
+        line.starts_with(PROGRESS_MARKER) || line.starts_with(WARNING_MARKER)
+    }
+
+    fn parse_phase(&mut self, fields: &str) -> Option<Progress> {
+        let mut phase = None;
+        let mut percent = None;
+        for field in fields.split_whitespace() {
+            match field.split_once('=') {
+                Some(("phase", v)) => phase = Phase::parse(v),
+                Some(("percent", v)) => percent = v.parse::<u8>().ok(),
+                _ => {}
+            }
+        }
+
+        let phase = phase?;
+        let percent = match phase {
+            Phase::Done => 100,
+            _ => percent.unwrap_or(self.percent).min(99),
+        };
+        self.percent = self.percent.max(percent);
+        Some(Progress::Phase {
+            phase,
+            percent: self.percent,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::context::{AppContext, Headless};
+    use crate::helper::exec_script;
+    use crate::platform::fake::{FakeBackend, FakeState};
+    use crate::runner::replay::ReplayRunner;
+
+    fn phase(phase: Phase, percent: u8) -> Progress {
+        Progress::Phase { phase, percent }
+    }
+
+    fn warning(message: &str) -> Progress {
+        Progress::Warning {
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn feed() {
+        let mut parser = ProgressParser::default();
+        let cases = [
+            (
+                "::progress phase=add-repo percent=10",
+                Some(phase(Phase::AddRepo, 10)),
+            ),
+            // the percentage never goes backwards
+            (
+                "::progress phase=add-repo percent=5",
+                Some(phase(Phase::AddRepo, 10)),
+            ),
+            (
+                "::progress phase=update-index",
+                Some(phase(Phase::UpdateIndex, 10)),
+            ),
+            (
+                "::progress percent=120 phase=wait-daemon",
+                Some(phase(Phase::WaitDaemon, 99)),
+            ),
+            ("::progress phase=unknown percent=50", None),
+            (
+                "::warning  disk almost full ",
+                Some(warning("disk almost full")),
+            ),
+            ("W: legacy keyring", Some(warning("legacy keyring"))),
+            ("Warning: retrying", Some(warning("retrying"))),
+            ("Unpacking sonaric (1.1.0) ...", None),
+            (
+                "::progress phase=done percent=20",
+                Some(phase(Phase::Done, 100)),
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parser.feed(line), expected, "{}", line);
+        }
+    }
+
+    /// Replays a recorded install and returns its progress and output events.
+    async fn replay(fixture: &str) -> (Vec<serde_json::Value>, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        let ctx = AppContext::Headless(Arc::new(Headless {
+            on_event: Box::new(move |event, payload| {
+                sink.lock().unwrap().push((event.to_string(), payload));
+            }),
+            ..Headless::test(
+                Arc::new(FakeBackend::new(FakeState::Missing, None)),
+                dir.path(),
+            )
+        }));
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(fixture);
+        let runner = ReplayRunner::load(&fixtures).unwrap();
+        let args = vec!["/usr/bin/sh", "/tmp/sonaric-install.sh", "stable"];
+        exec_script(ctx, &runner, "/usr/bin/pkexec", args, true, true)
+            .await
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        let payloads = |name: &str| {
+            events
+                .iter()
+                .filter(|(event, _)| event == name)
+                .map(|(_, payload)| payload.clone())
+                .collect::<Vec<_>>()
+        };
+        let output = payloads("install-output")
+            .iter()
+            .map(|payload| payload.as_str().unwrap().to_string())
+            .collect();
+        (payloads("install-progress"), output)
+    }
+
+    fn events(progress: Vec<Progress>) -> Vec<serde_json::Value> {
+        progress
+            .iter()
+            .map(|p| serde_json::to_value(p).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn install_apt() {
+        let (progress, output) = replay("install-linux-apt").await;
+        assert_eq!(
+            progress,
+            events(vec![
+                phase(Phase::DetectDistro, 5),
+                phase(Phase::AddRepo, 10),
+                phase(Phase::UpdateIndex, 30),
+                warning("https://us-central1-apt.pkg.dev/projects/sonaric-platform/dists/sonaric-releases-apt/InRelease: Key is stored in legacy trusted.gpg keyring (/etc/apt/trusted.gpg), see the DEPRECATION section in apt-key(8) for details."),
+                phase(Phase::InstallPackage, 50),
+                phase(Phase::Done, 100),
+            ])
+        );
+        assert!(!output.iter().any(|ln| ProgressParser::is_marker(ln)));
+        assert!(output.contains(&"Setting up sonaric (1.1.0) ...".to_string()));
+    }
+
+    #[tokio::test]
+    async fn install_update() {
+        let (progress, output) = replay("install-linux-update").await;
+        assert_eq!(
+            progress,
+            events(vec![
+                phase(Phase::DetectDistro, 5),
+                phase(Phase::AddRepo, 15),
+                phase(Phase::UpdateIndex, 30),
+                phase(Phase::InstallPackage, 50),
+                phase(Phase::WaitDaemon, 75),
+                phase(Phase::PullGui, 85),
+                warning("sonaric-gui is restarting, the dashboard may be unavailable for a minute"),
+                phase(Phase::Done, 100),
+            ])
+        );
+        assert!(!output.iter().any(|ln| ProgressParser::is_marker(ln)));
+        assert!(output.contains(&"Workloads updated".to_string()));
+    }
+}
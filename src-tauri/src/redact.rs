@@ -0,0 +1,420 @@
+use std::borrow::Cow;
+use std::io::Write;
+use std::sync::{Arc, RwLock};
+
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Redactor applied by the file log layer, the bug reports and the diagnostics.
+static CURRENT: RwLock<Option<Arc<Redactor>>> = RwLock::new(None);
+
+/// Redaction part of the settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionSettings {
+    pub enabled: bool,
+    /// Names of the built-in rules to skip, e.g. `ip`.
+    pub disabled_rules: Vec<String>,
+    /// Additional rules, applied after the built-in ones.
+    pub rules: Vec<RuleConfig>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled_rules: vec![],
+            rules: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub name: String,
+    pub pattern: String,
+    /// Replacement of the match, may refer to groups of the pattern, e.g. `$1`.
+    pub replacement: String,
+}
+
+pub struct Rule {
+    pub name: String,
+    pattern: Regex,
+    replacement: String,
+    // matches that are kept as is, e.g. loopback addresses
+    keep: Option<Regex>,
+}
+
+impl Rule {
+    pub fn new(name: &str, pattern: &str, replacement: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name: name.to_string(),
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.to_string(),
+            keep: None,
+        })
+    }
+
+    fn keep(mut self, pattern: &str) -> Self {
+        self.keep = Some(Regex::new(pattern).expect("valid keep pattern"));
+        self
+    }
+
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.pattern
+            .replace_all(text, |caps: &Captures| match &self.keep {
+                Some(keep) if keep.is_match(&caps[0]) => caps[0].to_string(),
+                _ => {
+                    let mut out = String::new();
+                    caps.expand(&self.replacement, &mut out);
+                    out
+                }
+            })
+    }
+}
+
+/// Built-in rules, in the order they're applied.
+pub fn builtin_rules() -> Vec<Rule> {
+    let rules = [
+        // PEM blocks and raw 32 byte keys, before the shorter hex rules
+        (
+            "key",
+            r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+            "[PRIVATE KEY]",
+        ),
+        // labeled sha256 digests, e.g. of the bundle packages, are kept
+        ("key", r"(?i:sha256:)?\b(0x)?[0-9a-fA-F]{64}\b", "[KEY]"),
+        ("wallet", r"\b0x[0-9a-fA-F]{40}\b", "[ADDRESS]"),
+        // any credentials of an `Authorization` header, elsewhere only token-like ones
+        // so that prose like "Basic setup complete" is kept
+        (
+            "token",
+            r"(?i)\b(authorization\s*:\s*(?:bearer|basic))\s+[A-Za-z0-9._~+/-]+=*",
+            "$1 [TOKEN]",
+        ),
+        (
+            "token",
+            r"(?i)\b(bearer|basic)\s+[A-Za-z0-9._~+/-]{16,}=*",
+            "$1 [TOKEN]",
+        ),
+        (
+            "token",
+            r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+            "[TOKEN]",
+        ),
+        (
+            "token",
+            r#"(?i)\b((?:access_|api_|auth_|refresh_)?(?:token|secret|password|passwd|api[_-]?key))(["']?\s*[:=]\s*["']?)[^\s"',;&]+"#,
+            "$1$2[TOKEN]",
+        ),
+        (
+            "email",
+            r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b",
+            "[EMAIL]",
+        ),
+        (
+            "home",
+            r"(/home/|/Users/|[A-Za-z]:\\Users\\|\\\\wsl\.localhost\\[^\\]+\\home\\)[^/\\\s:\x22']+",
+            "$1[USER]",
+        ),
+        // matches the version prefix and suffix too, so that four part versions like the
+        // WSL kernel `5.15.146.1-microsoft-standard-WSL2` are kept
+        (
+            "ip",
+            r"(?i:\bversion:?\s*v?|\bv|\b)(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)(?:\.(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)){3}\b(?:\.\d+|-[A-Za-z0-9])?",
+            "[IP]",
+        ),
+        (
+            "ip",
+            r"\b(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}\b|\b(?:[0-9a-fA-F]{1,4}:){1,6}:(?:[0-9a-fA-F]{1,4}:){0,5}[0-9a-fA-F]{1,4}\b",
+            "[IP]",
+        ),
+    ];
+
+    rules
+        .into_iter()
+        .map(|(name, pattern, replacement)| {
+            let rule = Rule::new(name, pattern, replacement).expect("valid built-in rule");
+            match name {
+                "key" => rule.keep(r"(?i)^sha256:"),
+                "ip" => rule.keep(
+                    r"(?i)^(v|version)|(\.\d+){4}$|-[a-z0-9]$|^(127\.\d+\.\d+\.\d+|0\.0\.0\.0|::1)$",
+                ),
+                _ => rule,
+            }
+        })
+        .collect()
+}
+
+/// Ordered set of redaction rules.
+#[derive(Default)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+}
+
+impl Redactor {
+    /// Builds the redactor of the settings, invalid custom rules are skipped.
+    pub fn new(settings: &RedactionSettings) -> Self {
+        if !settings.enabled {
+            return Self::default();
+        }
+
+        let mut rules: Vec<Rule> = builtin_rules()
+            .into_iter()
+            .filter(|r| !settings.disabled_rules.contains(&r.name))
+            .collect();
+        for rule in &settings.rules {
+            match Rule::new(&rule.name, &rule.pattern, &rule.replacement) {
+                Ok(rule) => rules.push(rule),
+                Err(e) => tracing::warn!("skip redaction rule {}: {}", rule.name, e),
+            }
+        }
+        Self { rules }
+    }
+
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for rule in &self.rules {
+            if let Cow::Owned(redacted) = rule.apply(&text) {
+                text = Cow::Owned(redacted);
+            }
+        }
+        text
+    }
+}
+
+/// Replaces the redactor used from now on.
+pub fn configure(settings: &RedactionSettings) {
+    // built before taking the lock, the log writer reads it for the warnings
+    let redactor = Arc::new(Redactor::new(settings));
+    *CURRENT.write().unwrap() = Some(redactor);
+}
+
+/// Returns the configured redactor, the default rules until the settings are loaded.
+pub fn current() -> Arc<Redactor> {
+    if let Some(redactor) = CURRENT.read().unwrap().as_ref() {
+        return redactor.clone();
+    }
+    CURRENT
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(Redactor::new(&RedactionSettings::default())))
+        .clone()
+}
+
+/// Redacts `text` with the current redactor.
+pub fn redact(text: &str) -> String {
+    current().redact(text).into_owned()
+}
+
+/// Log writer redacting every event before it reaches the inner writer.
+pub struct RedactingWriter<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingWriter<M> {
+    type Writer = Redacting<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Redacting {
+            inner: self.0.make_writer(),
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// Buffers one event, the fmt layer drops the writer once the event is written.
+pub struct Redacting<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> Write for Redacting<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buf.is_empty() {
+            let text = String::from_utf8_lossy(&self.buf);
+            self.inner.write_all(current().redact(&text).as_bytes())?;
+            self.buf.clear();
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for Redacting<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(name: &str, text: &str) -> String {
+        let mut text = text.to_string();
+        for rule in builtin_rules().iter().filter(|r| r.name == name) {
+            text = rule.apply(&text).into_owned();
+        }
+        text
+    }
+
+    #[test]
+    fn rules() {
+        let digest = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let cases = [
+            // (rule, text, redacted)
+            (
+                "key",
+                format!("private key {}", digest),
+                "private key [KEY]".to_string(),
+            ),
+            ("key", format!("0x{}", digest), "[KEY]".to_string()),
+            (
+                "key",
+                format!("expected sha256:{}", digest),
+                format!("expected sha256:{}", digest),
+            ),
+            (
+                "key",
+                format!("SHA256:{}", digest),
+                format!("SHA256:{}", digest),
+            ),
+            ("key", "sha256:abc".to_string(), "sha256:abc".to_string()),
+            (
+                "wallet",
+                "to 0x52908400098527886E0F7030069857D2E4169EE7".to_string(),
+                "to [ADDRESS]".to_string(),
+            ),
+            (
+                "token",
+                "Authorization: Bearer abc.def".to_string(),
+                "Authorization: Bearer [TOKEN]".to_string(),
+            ),
+            (
+                "token",
+                "authorization:basic dXNlcjpwYXNz".to_string(),
+                "authorization:basic [TOKEN]".to_string(),
+            ),
+            (
+                "token",
+                "retry with bearer 3f9a0c1d7e5b42a8b6c1==".to_string(),
+                "retry with bearer [TOKEN]".to_string(),
+            ),
+            (
+                "token",
+                "Basic setup complete".to_string(),
+                "Basic setup complete".to_string(),
+            ),
+            (
+                "token",
+                "bearer tokens expire".to_string(),
+                "bearer tokens expire".to_string(),
+            ),
+            (
+                "token",
+                "api_key=s3cr3t&x=1".to_string(),
+                "api_key=[TOKEN]&x=1".to_string(),
+            ),
+            (
+                "email",
+                "from jane@example.com".to_string(),
+                "from [EMAIL]".to_string(),
+            ),
+            (
+                "home",
+                "/home/jane/.sonaric".to_string(),
+                "/home/[USER]/.sonaric".to_string(),
+            ),
+            (
+                "ip",
+                "connect 10.0.0.5:44006".to_string(),
+                "connect [IP]:44006".to_string(),
+            ),
+            (
+                "ip",
+                "peer 192.168.1.20.".to_string(),
+                "peer [IP].".to_string(),
+            ),
+            (
+                "ip",
+                "listen 127.0.0.1 and 0.0.0.0".to_string(),
+                "listen 127.0.0.1 and 0.0.0.0".to_string(),
+            ),
+            (
+                "ip",
+                "fe80:0:0:0:202:b3ff:fe1e:8329".to_string(),
+                "[IP]".to_string(),
+            ),
+            ("ip", "::1".to_string(), "::1".to_string()),
+            // four part versions
+            (
+                "ip",
+                "Kernel version: 5.15.146.1-2".to_string(),
+                "Kernel version: 5.15.146.1-2".to_string(),
+            ),
+            (
+                "ip",
+                "WSL version: 2.1.5.0".to_string(),
+                "WSL version: 2.1.5.0".to_string(),
+            ),
+            (
+                "ip",
+                "5.15.146.1-microsoft-standard-WSL2".to_string(),
+                "5.15.146.1-microsoft-standard-WSL2".to_string(),
+            ),
+            (
+                "ip",
+                "sonaric v1.4.2.7".to_string(),
+                "sonaric v1.4.2.7".to_string(),
+            ),
+            (
+                "ip",
+                "build 1.2.3.4.5".to_string(),
+                "build 1.2.3.4.5".to_string(),
+            ),
+            (
+                "ip",
+                "not 300.1.1.1".to_string(),
+                "not 300.1.1.1".to_string(),
+            ),
+        ];
+        for (name, text, redacted) in cases {
+            assert_eq!(apply(name, &text), redacted, "{}: {}", name, text);
+        }
+    }
+
+    #[test]
+    fn disabled_and_custom_rules() {
+        let settings = RedactionSettings {
+            enabled: true,
+            disabled_rules: vec!["ip".to_string()],
+            rules: vec![
+                RuleConfig {
+                    name: "node".to_string(),
+                    pattern: r"node-(\w+)".to_string(),
+                    replacement: "node-[ID]".to_string(),
+                },
+                RuleConfig {
+                    name: "invalid".to_string(),
+                    pattern: "(".to_string(),
+                    replacement: String::new(),
+                },
+            ],
+        };
+        let redactor = Redactor::new(&settings);
+        assert_eq!(
+            redactor.redact("node-a1 at 10.0.0.5 by jane@example.com"),
+            "node-[ID] at 10.0.0.5 by [EMAIL]"
+        );
+
+        let disabled = Redactor::new(&RedactionSettings {
+            enabled: false,
+            ..settings
+        });
+        assert_eq!(disabled.redact("jane@example.com"), "jane@example.com");
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::header::{HeaderMap, ACCEPT, AUTHORIZATION, LINK, WWW_AUTHENTICATE};
+use reqwest::{Method, Response, StatusCode};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::Error;
+
+/// Manifest media types a tag may point to, so the registry returns the digest of
+/// the manifest as pushed instead of a converted one.
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+application/vnd.oci.image.manifest.v1+json, \
+application/vnd.docker.distribution.manifest.list.v2+json, \
+application/vnd.docker.distribution.manifest.v2+json";
+
+const PAGE_SIZE: u32 = 1000;
+/// Pages of the tag list read at most, in case the registry keeps linking pages.
+const MAX_PAGES: usize = 100;
+/// Manifests looked up at most to find the release of a tag, the moving tags point
+/// to one of the latest releases.
+const MAX_DIGESTS: usize = 20;
+
+static NEXT_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"<([^>]+)>\s*;\s*rel="?next"?"#).unwrap());
+static CHALLENGE_PARAM: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+
+#[derive(Deserialize)]
+struct TagList {
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Extension of Google registries: the manifests by digest with their tags.
+    #[serde(default)]
+    manifest: HashMap<String, GoogleManifest>,
+}
+
+#[derive(Deserialize)]
+struct GoogleManifest {
+    #[serde(default)]
+    tag: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+/// Minimal OCI distribution client for one repository, anonymous or with the
+/// bearer tokens the registry hands out.
+pub struct RegistryClient {
+    /// Repository URL, `https://{host}/v2/{name}`.
+    repo_url: String,
+    client: reqwest::Client,
+    token: Mutex<Option<String>>,
+}
+
+impl RegistryClient {
+    pub fn new(repo_url: &str) -> Self {
+        Self {
+            repo_url: repo_url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Returns the semver tags up to the highest one pointing to the same manifest as
+    /// `tag`, highest first.
+    pub async fn releases_up_to(&self, tag: &str) -> Result<Vec<Version>, Error> {
+        let (tags, digests) = self.tags().await?;
+
+        let mut versions: Vec<(Version, String)> = tags
+            .iter()
+            .filter_map(|t| Some((parse_tag(t)?, t.clone())))
+            .collect();
+        versions.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let highest = self.highest_version_of(tag, &versions, &digests).await?;
+        Ok(versions
+            .into_iter()
+            .map(|(v, _)| v)
+            .filter(|v| v.le(&highest))
+            .collect())
+    }
+
+    /// Returns the highest of `versions` pointing to the same manifest as `tag`.
+    async fn highest_version_of(
+        &self,
+        tag: &str,
+        versions: &[(Version, String)],
+        digests: &HashMap<String, String>,
+    ) -> Result<Version, Error> {
+        // the tag list of Google registries already has the digests
+        if let Some(target) = digests.get(tag) {
+            return versions
+                .iter()
+                .find(|(_, t)| digests.get(t) == Some(target))
+                .map(|(v, _)| v.clone())
+                .ok_or(Error::from(anyhow!("No release tagged with {}", tag)));
+        }
+
+        let target = self.digest(tag).await?;
+        for (version, t) in versions.iter().take(MAX_DIGESTS) {
+            match self.digest(t).await {
+                Ok(digest) if digest == target => return Ok(version.clone()),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("get digest of {}: {}", t, e),
+            }
+        }
+        Err(Error::from(anyhow!(
+            "No release tagged with {} among the latest {}",
+            tag,
+            MAX_DIGESTS
+        )))
+    }
+
+    /// Lists the tags, following the pagination links, with the digests if the
+    /// registry lists them.
+    async fn tags(&self) -> Result<(Vec<String>, HashMap<String, String>), Error> {
+        let mut tags = vec![];
+        let mut digests = HashMap::new();
+        let mut url = format!("{}/tags/list?n={}", self.repo_url, PAGE_SIZE);
+        for page in 1.. {
+            let resp = self.send(Method::GET, &url, None).await?;
+            let next = next_link(resp.headers());
+            let list: TagList = resp.json().await?;
+
+            tags.extend(list.tags);
+            for (digest, manifest) in list.manifest {
+                for tag in manifest.tag {
+                    digests.insert(tag, digest.clone());
+                }
+            }
+
+            let next = match next {
+                Some(next) => self.resolve(&next),
+                None => break,
+            };
+            if next == url || page == MAX_PAGES {
+                tracing::warn!("stop listing the tags of {} at {}", self.repo_url, url);
+                break;
+            }
+            url = next;
+        }
+        tracing::debug!("{} tags in {}", tags.len(), self.repo_url);
+        Ok((tags, digests))
+    }
+
+    /// Digest of the manifest `reference` points to.
+    async fn digest(&self, reference: &str) -> Result<String, Error> {
+        let url = format!("{}/manifests/{}", self.repo_url, reference);
+        let resp = self.send(Method::HEAD, &url, Some(MANIFEST_TYPES)).await?;
+        if let Some(digest) = header(resp.headers(), "docker-content-digest") {
+            return Ok(digest);
+        }
+
+        // the header is optional, the digest is the hash of the manifest
+        let resp = self.send(Method::GET, &url, Some(MANIFEST_TYPES)).await?;
+        if let Some(digest) = header(resp.headers(), "docker-content-digest") {
+            return Ok(digest);
+        }
+        let body = resp.bytes().await?;
+        Ok(format!("sha256:{}", hex::encode(Sha256::digest(body))))
+    }
+
+    /// Sends the request, answering a bearer challenge once.
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        accept: Option<&str>,
+    ) -> Result<Response, Error> {
+        let resp = self.request(method.clone(), url, accept).send().await?;
+        if resp.status() != StatusCode::UNAUTHORIZED {
+            return Ok(resp.error_for_status()?);
+        }
+
+        let challenge = header(resp.headers(), WWW_AUTHENTICATE.as_str())
+            .ok_or(anyhow!("{} requires authentication", url))?;
+        let token = self.authenticate(&challenge).await?;
+        *self.token.lock().unwrap() = Some(token);
+
+        let resp = self.request(method, url, accept).send().await?;
+        Ok(resp.error_for_status()?)
+    }
+
+    fn request(&self, method: Method, url: &str, accept: Option<&str>) -> reqwest::RequestBuilder {
+        let mut req = self.client.request(method, url);
+        if let Some(accept) = accept {
+            req = req.header(ACCEPT, accept);
+        }
+        if let Some(token) = self.token.lock().unwrap().as_ref() {
+            req = req.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        req
+    }
+
+    /// Gets an anonymous token for a `Bearer realm="..",service="..",scope=".."` challenge.
+    async fn authenticate(&self, challenge: &str) -> Result<String, Error> {
+        let params = parse_challenge(challenge).ok_or(anyhow!(
+            "Unsupported authentication challenge: {}",
+            challenge
+        ))?;
+        let realm = params
+            .get("realm")
+            .ok_or(anyhow!("No realm in challenge: {}", challenge))?;
+        let query: Vec<(&str, &str)> = ["service", "scope"]
+            .iter()
+            .filter_map(|key| Some((*key, params.get(*key)?.as_str())))
+            .collect();
+
+        tracing::debug!("get registry token from {}", realm);
+        let resp: TokenResponse = self
+            .client
+            .get(realm)
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        resp.token.or(resp.access_token).ok_or(Error::from(anyhow!(
+            "No token in the response of {}",
+            realm
+        )))
+    }
+
+    /// Resolves a pagination link, usually relative to the registry host.
+    fn resolve(&self, link: &str) -> String {
+        if link.starts_with("http://") || link.starts_with("https://") {
+            return link.to_string();
+        }
+        let host_end = self
+            .repo_url
+            .find("://")
+            .and_then(|i| self.repo_url[i + 3..].find('/').map(|j| i + 3 + j))
+            .unwrap_or(self.repo_url.len());
+        format!("{}{}", &self.repo_url[..host_end], link)
+    }
+}
+
+fn header(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers.get(name)?.to_str().ok().map(str::to_string)
+}
+
+/// Target of a `Link: </v2/name/tags/list?n=100&last=x>; rel="next"` header.
+fn next_link(headers: &HeaderMap) -> Option<String> {
+    let link = header(headers, LINK.as_str())?;
+    NEXT_LINK.captures(&link).map(|c| c[1].to_string())
+}
+
+fn parse_challenge(challenge: &str) -> Option<HashMap<String, String>> {
+    let params = challenge.strip_prefix("Bearer ")?;
+    Some(
+        CHALLENGE_PARAM
+            .captures_iter(params)
+            .map(|c| (c[1].to_string(), c[2].to_string()))
+            .collect(),
+    )
+}
+
+/// Release tags are semver with a `v` prefix, e.g. `v1.4.2`.
+fn parse_tag(tag: &str) -> Option<Version> {
+    Version::parse(tag.strip_prefix('v')?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn challenge() {
+        let params = parse_challenge(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:sonaric/sonaricd:pull""#,
+        )
+        .unwrap();
+        assert_eq!(params["realm"], "https://ghcr.io/token");
+        assert_eq!(params["service"], "ghcr.io");
+        assert_eq!(params["scope"], "repository:sonaric/sonaricd:pull");
+
+        assert!(parse_challenge("Bearer").is_none());
+        assert!(parse_challenge(r#"Basic realm="registry""#).is_none());
+        assert!(parse_challenge("Bearer ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn pagination_link() {
+        let cases = [
+            (
+                r#"</v2/sonaric/tags/list?n=1000&last=v1.4.0>; rel="next""#,
+                Some("/v2/sonaric/tags/list?n=1000&last=v1.4.0"),
+            ),
+            (
+                "<https://registry.example.com/v2/sonaric/tags/list?last=b>;rel=next",
+                Some("https://registry.example.com/v2/sonaric/tags/list?last=b"),
+            ),
+            (r#"</v2/sonaric/tags/list?n=1000>; rel="prev""#, None),
+        ];
+        for (link, next) in cases {
+            let mut headers = HeaderMap::new();
+            headers.insert(LINK, HeaderValue::from_static(link));
+            assert_eq!(next_link(&headers).as_deref(), next, "{}", link);
+        }
+        assert_eq!(next_link(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn tags() {
+        let cases = [
+            ("v1.4.2", Some(Version::new(1, 4, 2))),
+            ("v1.5.0-rc.1", Version::parse("1.5.0-rc.1").ok()),
+            ("1.4.2", None),
+            ("v1.4", None),
+            ("latest", None),
+            ("stable", None),
+        ];
+        for (tag, version) in cases {
+            assert_eq!(parse_tag(tag), version, "{}", tag);
+        }
+    }
+
+    /// Serves a repository `sonaric` with the tags split in two pages behind a bearer
+    /// challenge, and the anonymous `looping` and `many`, returns the URL of the host.
+    fn serve_registry() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = format!("http://{}", listener.local_addr().unwrap());
+        let base = host.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = vec![];
+                let mut buf = [0; 4096];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request).to_lowercase();
+                let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+                let authorized = request.contains("authorization: bearer t0k3n");
+
+                let digest = |tag: &str| match tag {
+                    "stable" | "v1.4.1" => "sha256:b",
+                    "latest" | "v1.5.0-rc.1" => "sha256:c",
+                    _ => "sha256:a",
+                };
+                let (status, headers, body) = match path.as_str() {
+                    // links to itself
+                    "/v2/looping/tags/list?n=1000" => (
+                        "200 OK",
+                        "Link: </v2/looping/tags/list?n=1000>; rel=\"next\"\r\n".to_string(),
+                        r#"{"tags": ["v1.0.0"]}"#.to_string(),
+                    ),
+                    // one release per minor version, `old` is the first one
+                    "/v2/many/tags/list?n=1000" => {
+                        let mut tags: Vec<String> =
+                            (0..40).map(|minor| format!("\"v1.{}.0\"", minor)).collect();
+                        tags.push("\"old\"".to_string());
+                        ("200 OK", String::new(), format!(r#"{{"tags": [{}]}}"#, tags.join(", ")))
+                    }
+                    path if path.starts_with("/v2/many/manifests/") => {
+                        let tag = path.trim_start_matches("/v2/many/manifests/");
+                        let tag = if tag == "old" { "v1.0.0" } else { tag };
+                        (
+                            "200 OK",
+                            format!("Docker-Content-Digest: sha256:{}\r\n", tag),
+                            String::new(),
+                        )
+                    }
+                    "/token?service=registry&scope=repository%3asonaric%3apull" => {
+                        ("200 OK", String::new(), r#"{"token": "t0k3n"}"#.to_string())
+                    }
+                    _ if !authorized => (
+                        "401 Unauthorized",
+                        format!(
+                            "WWW-Authenticate: Bearer realm=\"{}/token\",service=\"registry\",scope=\"repository:sonaric:pull\"\r\n",
+                            base
+                        ),
+                        String::new(),
+                    ),
+                    "/v2/sonaric/tags/list?n=1000" => (
+                        "200 OK",
+                        "Link: </v2/sonaric/tags/list?n=1000&last=v1.4.0>; rel=\"next\"\r\n"
+                            .to_string(),
+                        r#"{"tags": ["latest", "v1.3.0", "v1.4.0"]}"#.to_string(),
+                    ),
+                    "/v2/sonaric/tags/list?n=1000&last=v1.4.0" => (
+                        "200 OK",
+                        String::new(),
+                        r#"{"tags": ["stable", "v1.4.1", "v1.5.0-rc.1"]}"#.to_string(),
+                    ),
+                    path => match path.strip_prefix("/v2/sonaric/manifests/") {
+                        Some(tag) => (
+                            "200 OK",
+                            format!("Docker-Content-Digest: {}\r\n", digest(tag)),
+                            String::new(),
+                        ),
+                        None => ("404 Not Found", String::new(), String::new()),
+                    },
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        host
+    }
+
+    #[tokio::test]
+    async fn releases_across_pages() {
+        let client = RegistryClient::new(&format!("{}/v2/sonaric", serve_registry()));
+        let cases = [
+            ("stable", vec!["1.4.1", "1.4.0", "1.3.0"]),
+            ("latest", vec!["1.5.0-rc.1", "1.4.1", "1.4.0", "1.3.0"]),
+            ("v1.3.0", vec!["1.4.0", "1.3.0"]),
+        ];
+        for (tag, releases) in cases {
+            let releases: Vec<Version> = releases
+                .iter()
+                .map(|v| Version::parse(v).unwrap())
+                .collect();
+            assert_eq!(
+                client.releases_up_to(tag).await.unwrap(),
+                releases,
+                "{}",
+                tag
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn tags_looping() {
+        let client = RegistryClient::new(&format!("{}/v2/looping", serve_registry()));
+        let (tags, _) = client.tags().await.unwrap();
+        assert_eq!(tags, vec!["v1.0.0"]);
+    }
+
+    #[tokio::test]
+    async fn digests_capped() {
+        let client = RegistryClient::new(&format!("{}/v2/many", serve_registry()));
+        let cases = [
+            ("v1.39.0", Ok(Version::new(1, 39, 0))),
+            ("v1.20.0", Ok(Version::new(1, 20, 0))),
+            (
+                "v1.19.0",
+                Err("No release tagged with v1.19.0 among the latest 20"),
+            ),
+            ("old", Err("No release tagged with old among the latest 20")),
+        ];
+        for (tag, expected) in cases {
+            let result = client.releases_up_to(tag).await;
+            match expected {
+                Ok(highest) => assert_eq!(result.unwrap()[0], highest, "{}", tag),
+                Err(error) => assert_eq!(result.unwrap_err().to_string(), error, "{}", tag),
+            }
+        }
+    }
+}
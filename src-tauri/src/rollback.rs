@@ -0,0 +1,391 @@
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::bundle::{self, Bundle};
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::operation::{self, OperationKind};
+use crate::update_policy::Product;
+
+/// Daemon version replaced by the last update, in the app data dir.
+const RECORD_FILE: &str = "daemon-rollback.json";
+
+/// Packages of the releases installed from bundles, `packages/{version}/` with the
+/// bundle manifest, so a rollback can reinstall them offline.
+const PACKAGES_DIR: &str = "packages";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreviousVersion {
+    /// Version to roll back to.
+    pub version: Version,
+    /// Version the update installed, `None` if the update left no working CLI.
+    pub replaced_by: Option<Version>,
+    /// Seconds since the epoch.
+    pub recorded_at: u64,
+}
+
+/// Runs an install or update, recording the daemon version it replaces for
+/// `rollback_daemon`. The packages of `bundle` are kept if the install succeeds.
+pub async fn recorded<F>(
+    ctx: &AppContext,
+    bundle: Option<&Bundle>,
+    install: F,
+) -> Result<String, Error>
+where
+    F: Future<Output = Result<String, Error>>,
+{
+    let before = ctx.platform().daemon_version(ctx.clone()).await.ok();
+    let result = install.await;
+
+    // a failed update may have replaced the packages already
+    if let Some(before) = before {
+        let after = ctx.platform().daemon_version(ctx.clone()).await.ok();
+        if after.as_ref() != Some(&before) {
+            tracing::info!("daemon updated from {} to {:?}", before, after);
+            let previous = PreviousVersion {
+                version: before,
+                replaced_by: after,
+                recorded_at: unix_time(),
+            };
+            if let Err(e) = save(ctx, &previous) {
+                tracing::warn!("record previous daemon version: {}", e);
+            }
+        }
+    }
+
+    if let (Ok(_), Some(bundle)) = (&result, bundle) {
+        if let Err(e) = keep_bundle(ctx, bundle) {
+            tracing::warn!("keep bundle {:?}: {}", bundle.dir, e);
+        }
+    }
+    result
+}
+
+/// Returns the version the last update replaced, if any.
+pub fn previous(ctx: &AppContext) -> Result<Option<PreviousVersion>, Error> {
+    let path = data_dir(ctx)?.join(RECORD_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+}
+
+fn save(ctx: &AppContext, previous: &PreviousVersion) -> Result<(), Error> {
+    let dir = data_dir(ctx)?;
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join(RECORD_FILE),
+        serde_json::to_string_pretty(previous)?,
+    )?;
+    Ok(())
+}
+
+/// Copies the packages of an installed bundle, keeping only the ones of this
+/// release and of the previous one.
+fn keep_bundle(ctx: &AppContext, bundle: &Bundle) -> Result<(), Error> {
+    let version = bundle.version()?;
+    let dir = data_dir(ctx)?.join(PACKAGES_DIR);
+    let dest = dir.join(version.to_string());
+
+    if !dest.exists() {
+        // copied next to the destination first, so a partial copy is never used
+        let tmp = dir.join(format!(".{}", version));
+        if tmp.exists() {
+            fs::remove_dir_all(&tmp)?;
+        }
+        fs::create_dir_all(&tmp)?;
+        for file in &bundle.manifest.files {
+            fs::copy(bundle.dir.join(&file.name), tmp.join(&file.name))?;
+        }
+        fs::write(
+            tmp.join(bundle::MANIFEST),
+            serde_json::to_string_pretty(&bundle.manifest)?,
+        )?;
+        fs::rename(&tmp, &dest)?;
+        tracing::info!("kept the packages of {} in {:?}", version, dest);
+    }
+
+    let previous = previous(ctx)?.map(|p| p.version);
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let kept = match entry.file_name().to_str().map(Version::parse) {
+            Some(Ok(v)) => v == version || Some(&v) == previous.as_ref(),
+            _ => continue,
+        };
+        if !kept {
+            tracing::debug!("remove kept packages {:?}", entry.path());
+            fs::remove_dir_all(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens the kept packages of `version`, if their checksums still match.
+async fn kept_bundle(ctx: &AppContext, version: &Version) -> Option<Bundle> {
+    let path = data_dir(ctx)
+        .ok()?
+        .join(PACKAGES_DIR)
+        .join(version.to_string());
+    if !path.is_dir() {
+        return None;
+    }
+    let res = tokio::task::spawn_blocking(move || Bundle::open(&path))
+        .await
+        .map_err(|e| Error::from(anyhow!("open bundle: {}", e)))
+        .and_then(|res| res);
+    match res {
+        Ok(bundle) => Some(bundle),
+        Err(e) => {
+            tracing::warn!("kept packages of {}: {}", version, e);
+            None
+        }
+    }
+}
+
+/// Reinstalls the daemon version replaced by the last update, from the kept packages
+/// or the package repository.
+#[tauri::command]
+pub async fn rollback_daemon(handle: tauri::AppHandle) -> Result<String, Error> {
+    tracing::info!("handle rollback_daemon");
+    rollback(handle.into()).await
+}
+
+pub(crate) async fn rollback(ctx: AppContext) -> Result<String, Error> {
+    operation::run(&ctx, OperationKind::Rollback, async {
+        let previous =
+            previous(&ctx)?.ok_or(anyhow!("No previous daemon version to roll back to"))?;
+        let version = previous.version;
+        ctx.emit("status", format!("Rolling back to Sonaric {}...", version))?;
+
+        let backend = ctx.platform();
+        match kept_bundle(&ctx, &version).await {
+            Some(bundle) => backend.install_bundle(ctx.clone(), &bundle, true).await?,
+            None => backend.install_version(ctx.clone(), &version).await?,
+        };
+
+        // the package managers may keep the newer version without failing
+        let installed = backend.daemon_version(ctx.clone()).await?;
+        if installed != version {
+            return Err(Error::from(anyhow!(
+                "Rollback to Sonaric {} failed, {} is installed",
+                version,
+                installed
+            )));
+        }
+        tracing::info!("daemon rolled back to {}", version);
+
+        // don't offer the release that was rolled back again
+        if let Some(replaced_by) = previous.replaced_by {
+            ctx.update_settings(|s| {
+                s.updates
+                    .skipped
+                    .insert(Product::Daemon, replaced_by.to_string());
+            })?;
+        }
+        fs::remove_file(data_dir(&ctx)?.join(RECORD_FILE))?;
+        Ok(format!("Sonaric rolled back to {}", version))
+    })
+    .await
+}
+
+fn data_dir(ctx: &AppContext) -> Result<PathBuf, Error> {
+    ctx.app_data_dir()
+        .ok_or(Error::from(anyhow!("No app data directory")))
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::context::Headless;
+    use crate::platform::fake::{FakeBackend, FakeState};
+
+    // sha256 of the package content `sonaric`
+    const PACKAGE_SHA256: &str = "766cab26809a1268fddff99677bac598722d54b5ed74c7bc18b06879ef68ed81";
+
+    fn context(state: FakeState, version: &str, dir: &Path) -> AppContext {
+        let backend = FakeBackend::new(state, Some(Version::parse(version).unwrap()));
+        AppContext::Headless(Arc::new(Headless::test(Arc::new(backend), dir)))
+    }
+
+    /// Writes a bundle of `version` with one package to `dir`.
+    fn write_bundle(dir: &Path, version: &str) {
+        let name = format!("sonaric_{}_amd64.deb", version);
+        let manifest = bundle::Manifest {
+            version: version.to_string(),
+            files: vec![bundle::BundleFile {
+                name: name.clone(),
+                sha256: PACKAGE_SHA256.to_string(),
+            }],
+        };
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join(name), "sonaric").unwrap();
+        fs::write(
+            dir.join(bundle::MANIFEST),
+            serde_json::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn record(ctx: &AppContext, version: &str, replaced_by: &str) {
+        let previous = PreviousVersion {
+            version: Version::parse(version).unwrap(),
+            replaced_by: Some(Version::parse(replaced_by).unwrap()),
+            recorded_at: 0,
+        };
+        save(ctx, &previous).unwrap();
+    }
+
+    #[tokio::test]
+    async fn recorded_versions() {
+        let cases = [
+            // (state, installed by the update, recorded version and replacement)
+            (
+                FakeState::Running,
+                Some("1.5.0"),
+                Some(("1.4.0", Some("1.5.0"))),
+            ),
+            (
+                FakeState::Stopped,
+                Some("1.5.0"),
+                Some(("1.4.0", Some("1.5.0"))),
+            ),
+            (FakeState::Running, Some("1.4.0"), None),
+            (FakeState::Missing, Some("1.5.0"), None),
+            // a failed update that left no working CLI
+            (FakeState::Running, None, Some(("1.4.0", None))),
+        ];
+        for (state, update, expected) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = context(state, "1.4.0", dir.path());
+            let backend = ctx.platform();
+            let install = async {
+                match update {
+                    Some(v) => {
+                        let version = Version::parse(v).unwrap();
+                        backend.install_version(ctx.clone(), &version).await
+                    }
+                    None => {
+                        backend.uninstall(ctx.clone()).await?;
+                        Err(Error::from(anyhow!("install failed")))
+                    }
+                }
+            };
+            let result = recorded(&ctx, None, install).await;
+            assert_eq!(result.is_ok(), update.is_some(), "{:?} {:?}", state, update);
+
+            let previous = previous(&ctx)
+                .unwrap()
+                .map(|p| (p.version.to_string(), p.replaced_by.map(|v| v.to_string())));
+            let expected = expected.map(|(v, r)| (v.to_string(), r.map(str::to_string)));
+            assert_eq!(previous, expected, "{:?} {:?}", state, update);
+        }
+    }
+
+    #[tokio::test]
+    async fn keep_bundles() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(FakeState::Running, "1.3.0", dir.path());
+        let packages = dir.path().join(PACKAGES_DIR);
+        for version in ["1.2.0", "1.3.0"] {
+            write_bundle(&packages.join(version), version);
+        }
+        fs::create_dir_all(packages.join("notes")).unwrap();
+        write_bundle(&dir.path().join("bundle"), "1.4.0");
+        let bundle = Bundle::open(&dir.path().join("bundle")).unwrap();
+
+        let backend = ctx.platform();
+        let install = backend.install_bundle(ctx.clone(), &bundle, false);
+        recorded(&ctx, Some(&bundle), install).await.unwrap();
+
+        // the current release, the one it replaced and the unknown entries are left
+        let mut kept: Vec<String> = fs::read_dir(&packages)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        kept.sort();
+        assert_eq!(kept, vec!["1.3.0", "1.4.0", "notes"]);
+        let reopened = kept_bundle(&ctx, &Version::new(1, 4, 0)).await.unwrap();
+        assert_eq!(reopened.manifest.files.len(), 1);
+
+        // a failed install keeps no packages
+        write_bundle(&dir.path().join("next"), "1.5.0");
+        let next = Bundle::open(&dir.path().join("next")).unwrap();
+        let failed = async { Err(Error::from(anyhow!("install failed"))) };
+        assert!(recorded(&ctx, Some(&next), failed).await.is_err());
+        assert!(!packages.join("1.5.0").exists());
+    }
+
+    #[tokio::test]
+    async fn rollback_versions() {
+        let cases = [
+            // (version of the kept packages, error)
+            (None, None),
+            (Some("1.4.0"), None),
+            // the package manager kept another version
+            (
+                Some("1.3.0"),
+                Some("Rollback to Sonaric 1.4.0 failed, 1.3.0 is installed"),
+            ),
+        ];
+        for (kept, error) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let ctx = context(FakeState::Running, "1.5.0", dir.path());
+            record(&ctx, "1.4.0", "1.5.0");
+            if let Some(version) = kept {
+                write_bundle(&dir.path().join(PACKAGES_DIR).join("1.4.0"), version);
+            }
+
+            let result = rollback(ctx.clone()).await;
+            let skipped = ctx
+                .settings()
+                .updates
+                .skipped
+                .get(&Product::Daemon)
+                .cloned();
+            match error {
+                None => {
+                    assert_eq!(result.unwrap(), "Sonaric rolled back to 1.4.0");
+                    assert_eq!(
+                        ctx.platform().daemon_version(ctx.clone()).await.unwrap(),
+                        Version::new(1, 4, 0)
+                    );
+                    // the replaced release isn't offered again
+                    assert_eq!(skipped.as_deref(), Some("1.5.0"), "{:?}", kept);
+                    assert!(previous(&ctx).unwrap().is_none());
+                }
+                Some(error) => {
+                    assert_eq!(result.unwrap_err().to_string(), error);
+                    assert_eq!(skipped, None);
+                    assert!(previous(&ctx).unwrap().is_some());
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rollback_without_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(FakeState::Running, "1.5.0", dir.path());
+        let err = rollback(ctx).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No previous daemon version to roll back to"
+        );
+    }
+}
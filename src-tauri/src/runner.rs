@@ -0,0 +1,265 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::operation::CancelToken;
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt as _;
+
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Raw result of a finished command.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CommandOutput {
+    pub(crate) code: Option<i32>,
+    pub(crate) stdout: Vec<u8>,
+    pub(crate) stderr: Vec<u8>,
+}
+
+impl CommandOutput {
+    pub(crate) fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Runs external commands, so that the callers can be exercised against recorded outputs.
+pub(crate) trait CommandRunner: Send + Sync {
+    /// Runs the command to completion and captures stdout and stderr.
+    fn output(&self, cmd: &str, args: &[&str]) -> Result<CommandOutput, Error>;
+
+    /// Runs the command with stderr merged into stdout and calls `on_line` for every line.
+    /// Returns the whole output, fails on a non-zero exit code if `check_status` is set
+    /// and with `Error::Cancelled` once `cancel` is triggered.
+    fn stream(
+        &self,
+        cmd: &str,
+        args: &[&str],
+        check_status: bool,
+        cancel: &CancelToken,
+        on_line: &mut dyn FnMut(&str) -> Result<(), Error>,
+    ) -> Result<String, Error>;
+}
+
+pub(crate) fn current() -> Arc<dyn CommandRunner> {
+    #[cfg(feature = "command-fixtures")]
+    if let Some(dir) = std::env::var_os("SONARIC_COMMAND_FIXTURES") {
+        match replay::ReplayRunner::load(std::path::Path::new(&dir)) {
+            Ok(runner) => {
+                tracing::warn!("replaying commands from {:?}", dir);
+                return Arc::new(runner);
+            }
+            Err(e) => tracing::error!("load command fixtures: {}", e),
+        }
+    }
+
+    Arc::new(SystemRunner)
+}
+
+/// Runs the commands on the host.
+pub(crate) struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, cmd: &str, args: &[&str]) -> Result<CommandOutput, Error> {
+        let mut command = Command::new(cmd);
+        command
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        #[cfg(windows)]
+        command.creation_flags(CREATE_NO_WINDOW);
+
+        let child = command.spawn()?;
+        let output = child.wait_with_output()?;
+        Ok(CommandOutput {
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
+    }
+
+    fn stream(
+        &self,
+        cmd: &str,
+        args: &[&str],
+        check_status: bool,
+        cancel: &CancelToken,
+        on_line: &mut dyn FnMut(&str) -> Result<(), Error>,
+    ) -> Result<String, Error> {
+        let mut h = duct::cmd(cmd, args).stderr_to_stdout().stdout_capture();
+        if !check_status {
+            h = h.unchecked();
+        }
+        // own process group, so that cancelling kills the whole tree
+        #[cfg(unix)]
+        let h = h.before_spawn(|c| {
+            c.process_group(0);
+            Ok(())
+        });
+
+        let reader = h.reader()?;
+        let _processes = cancel.register(reader.pids());
+
+        let mut output = String::new();
+        for line in BufReader::new(&reader).lines() {
+            let ln = match line {
+                _ if cancel.is_cancelled() => return Err(Error::Cancelled),
+                Ok(ln) => ln,
+                Err(e) => {
+                    // get last 8 lines from output
+                    let mut last_output = output.lines().rev().take(8).collect::<Vec<&str>>();
+                    last_output.reverse();
+
+                    // append output to error
+                    return Err(Error::from(
+                        anyhow::Error::new(e)
+                            .context(output.clone())
+                            .context(format!(
+                                "command execution failed:\n\n{}",
+                                last_output.join("\n"),
+                            )),
+                    ));
+                }
+            };
+            on_line(&ln)?;
+            // append to output result
+            output.push_str(format!("{}\n", ln).as_str());
+        }
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(any(test, feature = "command-fixtures"))]
+pub(crate) mod replay {
+    use std::collections::{HashMap, VecDeque};
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use anyhow::anyhow;
+    use serde::Deserialize;
+
+    use super::{CommandOutput, CommandRunner};
+    use crate::error::Error;
+    use crate::operation::CancelToken;
+
+    /// A recorded command, one JSON file per fixture.
+    #[derive(Deserialize)]
+    struct Fixture {
+        argv: Vec<String>,
+        #[serde(default)]
+        stdout: String,
+        #[serde(default)]
+        stderr: String,
+        #[serde(default)]
+        code: i32,
+        /// Encoding of stdout as produced by the command: `utf-8` or `utf-16le`.
+        #[serde(default)]
+        encoding: Option<String>,
+    }
+
+    impl Fixture {
+        fn output(&self) -> CommandOutput {
+            let stdout = match self.encoding.as_deref() {
+                Some("utf-16le") => self
+                    .stdout
+                    .encode_utf16()
+                    .flat_map(|u| u.to_le_bytes())
+                    .collect(),
+                _ => self.stdout.clone().into_bytes(),
+            };
+            CommandOutput {
+                code: Some(self.code),
+                stdout,
+                stderr: self.stderr.clone().into_bytes(),
+            }
+        }
+    }
+
+    /// Replays recorded outputs by argv. Fixtures with the same argv are returned
+    /// in file name order, the last one is repeated once the others are consumed.
+    pub(crate) struct ReplayRunner {
+        fixtures: Mutex<HashMap<Vec<String>, VecDeque<Fixture>>>,
+    }
+
+    impl ReplayRunner {
+        pub(crate) fn load(dir: &Path) -> Result<Self, Error> {
+            let mut paths = std::fs::read_dir(dir)?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect::<Vec<_>>();
+            paths.sort();
+
+            let mut fixtures: HashMap<Vec<String>, VecDeque<Fixture>> = HashMap::new();
+            for path in paths {
+                let fixture: Fixture = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+                fixtures
+                    .entry(fixture.argv.clone())
+                    .or_default()
+                    .push_back(fixture);
+            }
+
+            Ok(Self {
+                fixtures: Mutex::new(fixtures),
+            })
+        }
+
+        fn next(&self, cmd: &str, args: &[&str]) -> Result<CommandOutput, Error> {
+            let argv = std::iter::once(cmd)
+                .chain(args.iter().copied())
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            let mut fixtures = self.fixtures.lock().unwrap();
+            let queue = fixtures
+                .get_mut(&argv)
+                .ok_or(anyhow!("no fixture recorded for {:?}", argv))?;
+            let output = match queue.len() {
+                1 => queue.front().map(Fixture::output),
+                _ => queue.pop_front().map(|f| f.output()),
+            };
+            output.ok_or(Error::from(anyhow!("no fixture recorded for {:?}", argv)))
+        }
+    }
+
+    impl CommandRunner for ReplayRunner {
+        fn output(&self, cmd: &str, args: &[&str]) -> Result<CommandOutput, Error> {
+            self.next(cmd, args)
+        }
+
+        fn stream(
+            &self,
+            cmd: &str,
+            args: &[&str],
+            check_status: bool,
+            cancel: &CancelToken,
+            on_line: &mut dyn FnMut(&str) -> Result<(), Error>,
+        ) -> Result<String, Error> {
+            let res = self.next(cmd, args)?;
+            let mut output = String::from_utf8(res.stdout.clone())?;
+            output.push_str(&String::from_utf8(res.stderr.clone())?);
+            for ln in output.lines() {
+                if cancel.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+                on_line(ln)?;
+            }
+            if check_status && !res.success() {
+                return Err(Error::from(anyhow!(
+                    "command {:?} exited with code {:?}",
+                    cmd,
+                    res.code
+                )));
+            }
+            Ok(output)
+        }
+    }
+}
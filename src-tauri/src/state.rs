@@ -0,0 +1,307 @@
+use std::env;
+
+use anyhow::anyhow;
+use semver::Version;
+
+use crate::error::Error;
+use crate::update_policy::{Product, UpdateAction, UpdatePolicy};
+
+/// Platform backend used to run the node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Linux,
+    Macos,
+    Wsl,
+}
+
+impl Backend {
+    pub fn current() -> Result<Self, Error> {
+        match env::consts::OS {
+            "linux" => Ok(Backend::Linux),
+            "macos" => Ok(Backend::Macos),
+            "windows" => Ok(Backend::Wsl),
+            _ => Err(Error::from(anyhow!("Unsupported OS"))),
+        }
+    }
+}
+
+/// Component that has to be installed before the node can run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Component {
+    Wsl,
+    Wsl2,
+    Distribution,
+    Sonaric,
+}
+
+/// State of the local node as reported to the frontend by `check_install` and `check_gui`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum NodeState {
+    /// A component is missing, the install script has to run.
+    Install {
+        backend: Backend,
+        missing: Component,
+    },
+    /// Sonaric is installed but the daemon or the GUI is not reachable.
+    Start {
+        backend: Backend,
+        installed: Option<String>,
+        daemon: bool,
+        gui: bool,
+    },
+    /// Sonaric is installed and the update policy offers a newer release.
+    Update {
+        backend: Backend,
+        installed: String,
+        latest: String,
+        daemon: bool,
+        action: UpdateAction,
+    },
+    /// The daemon and the GUI are up.
+    Ready {
+        backend: Backend,
+        installed: Option<String>,
+        latest: Option<String>,
+    },
+}
+
+impl NodeState {
+    pub fn install(backend: Backend, missing: Component) -> Self {
+        NodeState::Install { backend, missing }
+    }
+
+    /// Resolves the state of an installed node from the `sonaric version` probe.
+    /// `daemon` tells whether the CLI could reach the daemon.
+    pub fn from_versions(
+        backend: Backend,
+        installed: Option<&Version>,
+        latest: Option<&Version>,
+        daemon: bool,
+        policy: &UpdatePolicy,
+    ) -> Self {
+        // only the latest release of the channel is published
+        let releases = Vec::from_iter(latest.cloned());
+        if let Some(installed) = installed {
+            if let Some((latest, action)) = policy.evaluate(Product::Daemon, installed, &releases) {
+                tracing::info!(
+                    "Update available: {} -> {} ({:?})",
+                    installed,
+                    latest,
+                    action
+                );
+                return NodeState::Update {
+                    backend,
+                    installed: installed.to_string(),
+                    latest: latest.to_string(),
+                    daemon,
+                    action,
+                };
+            }
+        }
+
+        if !daemon {
+            return NodeState::Start {
+                backend,
+                installed: installed.map(Version::to_string),
+                daemon: false,
+                gui: false,
+            };
+        }
+
+        NodeState::Ready {
+            backend,
+            installed: installed.map(Version::to_string),
+            latest: latest.map(Version::to_string),
+        }
+    }
+
+    /// Applies the GUI reachability to a state resolved from the daemon probe.
+    /// A running daemon without a reachable GUI still needs to be started.
+    pub fn with_gui(self, gui: bool) -> Self {
+        match self {
+            NodeState::Ready {
+                backend, installed, ..
+            } if !gui => NodeState::Start {
+                backend,
+                installed,
+                daemon: true,
+                gui: false,
+            },
+            state => state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::update_policy::UpdateMode;
+
+    fn v(s: &str) -> Version {
+        Version::parse(s).unwrap()
+    }
+
+    #[test]
+    fn install_reports_missing_component() {
+        for missing in [
+            Component::Wsl,
+            Component::Wsl2,
+            Component::Distribution,
+            Component::Sonaric,
+        ] {
+            assert_eq!(
+                NodeState::install(Backend::Wsl, missing),
+                NodeState::Install {
+                    backend: Backend::Wsl,
+                    missing
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn from_versions() {
+        let notify = UpdatePolicy::default();
+        let auto = UpdatePolicy {
+            mode: UpdateMode::Auto,
+            ..Default::default()
+        };
+        let skipped = UpdatePolicy {
+            skipped: [(Product::Daemon, "1.5.0".to_string())].into(),
+            ..Default::default()
+        };
+        let ready = |installed: &str, latest: Option<&str>| NodeState::Ready {
+            backend: Backend::Linux,
+            installed: Some(installed.to_string()),
+            latest: latest.map(str::to_string),
+        };
+        let update = |daemon, action| NodeState::Update {
+            backend: Backend::Linux,
+            installed: "1.4.0".to_string(),
+            latest: "1.5.0".to_string(),
+            daemon,
+            action,
+        };
+        let start = |installed: Option<&str>| NodeState::Start {
+            backend: Backend::Linux,
+            installed: installed.map(str::to_string),
+            daemon: false,
+            gui: false,
+        };
+
+        let cases = [
+            // installed, latest, daemon, policy, expected
+            (
+                Some("1.5.0"),
+                Some("1.5.0"),
+                true,
+                &notify,
+                ready("1.5.0", Some("1.5.0")),
+            ),
+            (Some("1.5.0"), None, true, &notify, ready("1.5.0", None)),
+            (
+                Some("1.6.0"),
+                Some("1.5.0"),
+                true,
+                &notify,
+                ready("1.6.0", Some("1.5.0")),
+            ),
+            (
+                Some("1.4.0"),
+                Some("1.5.0"),
+                true,
+                &notify,
+                update(true, UpdateAction::Notify),
+            ),
+            (
+                Some("1.4.0"),
+                Some("1.5.0"),
+                false,
+                &notify,
+                update(false, UpdateAction::Notify),
+            ),
+            (
+                Some("1.4.0"),
+                Some("1.5.0"),
+                true,
+                &auto,
+                update(true, UpdateAction::Install),
+            ),
+            (
+                Some("1.4.0"),
+                Some("1.5.0"),
+                true,
+                &skipped,
+                ready("1.4.0", Some("1.5.0")),
+            ),
+            (
+                Some("1.4.0"),
+                Some("1.5.0"),
+                false,
+                &skipped,
+                start(Some("1.4.0")),
+            ),
+            (
+                Some("1.5.0"),
+                Some("1.5.0"),
+                false,
+                &notify,
+                start(Some("1.5.0")),
+            ),
+            (None, Some("1.5.0"), false, &notify, start(None)),
+        ];
+        for (installed, latest, daemon, policy, expected) in cases {
+            let state = NodeState::from_versions(
+                Backend::Linux,
+                installed.map(v).as_ref(),
+                latest.map(v).as_ref(),
+                daemon,
+                policy,
+            );
+            assert_eq!(state, expected, "{:?} {:?} {}", installed, latest, daemon);
+        }
+    }
+
+    #[test]
+    fn with_gui() {
+        let ready = NodeState::Ready {
+            backend: Backend::Macos,
+            installed: Some("1.5.0".to_string()),
+            latest: None,
+        };
+        let update = NodeState::Update {
+            backend: Backend::Macos,
+            installed: "1.4.0".to_string(),
+            latest: "1.5.0".to_string(),
+            daemon: true,
+            action: UpdateAction::Notify,
+        };
+        let stopped = NodeState::Start {
+            backend: Backend::Macos,
+            installed: Some("1.5.0".to_string()),
+            daemon: false,
+            gui: false,
+        };
+        let gui_down = NodeState::Start {
+            backend: Backend::Macos,
+            installed: Some("1.5.0".to_string()),
+            daemon: true,
+            gui: false,
+        };
+
+        let cases = [
+            (ready.clone(), true, ready.clone()),
+            // the GUI probe failed while the daemon runs
+            (ready, false, gui_down),
+            (update.clone(), false, update),
+            (stopped.clone(), false, stopped.clone()),
+            (stopped.clone(), true, stopped),
+        ];
+        for (state, gui, expected) in cases {
+            assert_eq!(state.clone().with_gui(gui), expected, "{:?} {}", state, gui);
+        }
+    }
+}
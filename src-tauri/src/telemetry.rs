@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tauri::api::dialog;
+use tauri::Manager;
+
+use crate::error::Error;
+use crate::settings::{Consent, SettingsStore};
+
+/// Checked by the Sentry hooks, the client stays initialized so it can be toggled
+/// at runtime.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Client options dropping every event and breadcrumb while telemetry is off.
+pub fn client_options(release: String) -> sentry::ClientOptions {
+    sentry::ClientOptions {
+        release: Some(release.into()),
+        before_send: Some(Arc::new(|event| is_enabled().then_some(event))),
+        before_breadcrumb: Some(Arc::new(|breadcrumb| is_enabled().then_some(breadcrumb))),
+        ..Default::default()
+    }
+}
+
+/// Persists the consent and reflects it in the Help menu.
+pub fn set_consent(handle: &tauri::AppHandle, consent: Consent) -> Result<(), Error> {
+    tracing::info!("telemetry consent: {:?}", consent);
+    handle
+        .state::<SettingsStore>()
+        .update(|s| s.telemetry = consent)?;
+    if let Some(window) = handle.get_window("main") {
+        window
+            .menu_handle()
+            .get_item("telemetry")
+            .set_selected(consent == Consent::Granted)?;
+    }
+    Ok(())
+}
+
+/// Asks for the consent on the first start.
+pub fn prompt(handle: &tauri::AppHandle) {
+    if handle.state::<SettingsStore>().get().telemetry != Consent::Unknown {
+        return;
+    }
+
+    let window = handle.get_window("main");
+    let handle = handle.clone();
+    dialog::ask(
+        window.as_ref(),
+        "Crash Reports",
+        "Send crash reports and bug reports with logs to the Sonaric developers?\n\n\
+You can change this later in Help > Send Crash Reports.",
+        move |answer| {
+            let consent = if answer {
+                Consent::Granted
+            } else {
+                Consent::Denied
+            };
+            if let Err(e) = set_consent(&handle, consent) {
+                tracing::warn!("save telemetry consent: {}", e);
+            }
+        },
+    );
+}
+
+/// Toggles the consent from the Help menu.
+pub fn toggle(handle: &tauri::AppHandle) {
+    let consent = match handle.state::<SettingsStore>().get().telemetry {
+        Consent::Granted => Consent::Denied,
+        _ => Consent::Granted,
+    };
+    if let Err(e) = set_consent(handle, consent) {
+        tracing::warn!("save telemetry consent: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_telemetry(store: tauri::State<'_, SettingsStore>) -> Consent {
+    store.get().telemetry
+}
+
+#[tauri::command]
+pub fn set_telemetry(handle: tauri::AppHandle, consent: Consent) -> Result<(), Error> {
+    tracing::info!("handle set_telemetry: {:?}", consent);
+    set_consent(&handle, consent)
+}
@@ -0,0 +1,144 @@
+use tauri::api::dialog;
+use tauri::{
+    CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
+};
+
+use crate::health::Health;
+use crate::state::NodeState;
+use crate::version::refresh_versions;
+use crate::{check_install, install, start_daemon, stop_daemon, BaseUrl};
+
+pub fn build() -> SystemTray {
+    let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new("tray-status", "Sonaric: checking...").disabled())
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("tray-start", "Start"))
+        .add_item(CustomMenuItem::new("tray-stop", "Stop").disabled())
+        .add_item(CustomMenuItem::new("tray-dashboard", "Open Dashboard"))
+        .add_item(CustomMenuItem::new("tray-updates", "Check for Updates"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("tray-quit", "Quit"));
+
+    SystemTray::new().with_menu(menu).with_tooltip("Sonaric")
+}
+
+/// Reflects the node health in the tray menu.
+pub fn set_health(handle: &tauri::AppHandle, health: Health) -> Result<(), tauri::Error> {
+    let tray = handle.tray_handle();
+    let title = match health {
+        Health::Up => "Sonaric: running",
+        Health::Degraded => "Sonaric: degraded",
+        Health::Down => "Sonaric: stopped",
+    };
+    tray.get_item("tray-status").set_title(title)?;
+    tray.get_item("tray-start")
+        .set_enabled(health != Health::Up)?;
+    tray.get_item("tray-stop")
+        .set_enabled(health != Health::Down)?;
+    Ok(())
+}
+
+pub fn on_event(handle: &tauri::AppHandle, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_main_window(handle),
+        SystemTrayEvent::MenuItemClick { id, .. } => {
+            tracing::info!("tray event: {:?}", id);
+            match id.as_str() {
+                "tray-start" => start(handle.clone()),
+                "tray-stop" => stop(handle.clone()),
+                "tray-dashboard" => {
+                    show_main_window(handle);
+                    if let (Some(window), Some(base_url)) =
+                        (handle.get_window("main"), handle.try_state::<BaseUrl>())
+                    {
+                        let code = format!("window.location.href = '{}'", base_url.0);
+                        if let Err(e) = window.eval(code.as_str()) {
+                            tracing::warn!("open dashboard: {}", e);
+                        }
+                    }
+                }
+                "tray-updates" => check_updates(handle.clone()),
+                "tray-quit" => handle.exit(0),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn show_main_window(handle: &tauri::AppHandle) {
+    if let Some(window) = handle.get_window("main") {
+        if let Err(e) = window.show().and_then(|_| window.set_focus()) {
+            tracing::warn!("show window: {}", e);
+        }
+    }
+}
+
+fn start(handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        match check_install(handle.clone()).await {
+            Ok(NodeState::Start { .. }) => {
+                if let Err(e) = start_daemon(handle.clone()).await {
+                    tracing::error!("start from tray: {}", e);
+                    dialog::message(
+                        None::<&tauri::Window>,
+                        "Sonaric",
+                        format!("Failed to start Sonaric: {}", e),
+                    );
+                }
+            }
+            Ok(NodeState::Ready { .. }) => {}
+            Ok(NodeState::Update { action, .. }) if action.is_automatic() => {
+                if let Err(e) = install(handle.clone().into()).await {
+                    tracing::error!("update from tray: {}", e);
+                    dialog::message(
+                        None::<&tauri::Window>,
+                        "Sonaric",
+                        format!("Failed to update Sonaric: {}", e),
+                    );
+                }
+            }
+            // install and update need a confirmation in the window
+            Ok(_) => show_main_window(&handle),
+            Err(e) => tracing::error!("check install from tray: {}", e),
+        }
+    });
+}
+
+fn stop(handle: tauri::AppHandle) {
+    dialog::ask(
+        None::<&tauri::Window>,
+        "Stop Sonaric",
+        "Are you sure you want to stop Sonaric?",
+        move |answer| {
+            if !answer {
+                return;
+            }
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = stop_daemon(handle.clone()).await {
+                    tracing::error!("stop from tray: {}", e);
+                    dialog::message(
+                        None::<&tauri::Window>,
+                        "Sonaric",
+                        format!("Failed to stop Sonaric: {}", e),
+                    );
+                }
+            });
+        },
+    );
+}
+
+fn check_updates(handle: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mes = match refresh_versions(handle.clone()).await {
+            Ok(res) if res.app.up_to_date && res.daemon.up_to_date && res.gui.up_to_date => {
+                format!("Sonaric is up to date.\n\nApp version: {}\nDaemon version: {}\nGUI version: {}\n", res.app, res.daemon, res.gui)
+            }
+            Ok(res) => {
+                format!("An update is available.\n\nApp version: {}\nDaemon version: {}\nGUI version: {}\n", res.app, res.daemon, res.gui)
+            }
+            Err(e) => format!("Failed to check for updates: {}", e),
+        };
+        dialog::message(None::<&tauri::Window>, "Sonaric AI Node", mes);
+    });
+}
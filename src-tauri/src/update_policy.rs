@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use tauri::api::dialog::{self, MessageDialogBuilder, MessageDialogButtons};
+use tauri::Manager;
+
+use crate::context::AppContext;
+use crate::error::Error;
+use crate::settings::SettingsStore;
+use crate::version::lenient_version;
+
+/// Part of Sonaric with its own releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Product {
+    /// The desktop app, updated with the Tauri updater.
+    App,
+    Daemon,
+    /// The GUI image, pulled with the daemon. Its updates are only reported, the image
+    /// is updated with the daemon.
+    Gui,
+}
+
+/// Which releases are offered and whether they install on their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateMode {
+    /// Installs every release without asking.
+    Auto,
+    /// Offers every release.
+    #[default]
+    Notify,
+    /// Offers the patch releases of the installed minor version, which carry the
+    /// security fixes.
+    Security,
+    /// Offers the releases matching `pin`.
+    Pinned,
+}
+
+/// Update part of the settings, the same policy applies to the app and the daemon, and
+/// is reported for the GUI.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdatePolicy {
+    pub mode: UpdateMode,
+    /// Range of the `pinned` mode, a major (`1`) or minor (`1.4`) version, or a
+    /// semver requirement such as `>=1.2, <1.6`.
+    pub pin: Option<String>,
+    /// Releases the user skipped, a newer release is offered again.
+    pub skipped: HashMap<Product, String>,
+    /// Installed versions below these are updated without asking, whatever the mode.
+    pub min_versions: HashMap<Product, String>,
+}
+
+/// What to do about the latest release of a product.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateAction {
+    /// Up to date, or the policy holds the release back.
+    #[default]
+    None,
+    /// Offer the release, the user may skip it.
+    Notify,
+    /// Install the release without asking.
+    Install,
+    /// The installed version is below the minimum, install without asking or skipping.
+    Force,
+}
+
+impl UpdateAction {
+    /// Whether the update installs without the user.
+    pub fn is_automatic(&self) -> bool {
+        matches!(self, UpdateAction::Install | UpdateAction::Force)
+    }
+}
+
+impl UpdatePolicy {
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.mode == UpdateMode::Pinned && self.pin_req()?.is_none() {
+            return Err(Error::from(anyhow!("Pinned updates need a version range")));
+        }
+        for version in self.skipped.values().chain(self.min_versions.values()) {
+            if lenient_version(version).is_none() {
+                return Err(Error::from(anyhow!("Invalid version: {}", version)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses `pin`, a bare version like `1.4` pins that minor version.
+    fn pin_req(&self) -> Result<Option<VersionReq>, Error> {
+        let pin = match self.pin.as_deref().map(str::trim) {
+            Some(pin) if !pin.is_empty() => pin.trim_start_matches('v'),
+            _ => return Ok(None),
+        };
+        let req = if pin.starts_with(|c: char| c.is_ascii_digit()) {
+            VersionReq::parse(&format!("={}", pin))
+        } else {
+            VersionReq::parse(pin)
+        };
+        req.map(Some)
+            .map_err(|e| Error::from(anyhow!("Invalid version range {}: {}", pin, e)))
+    }
+
+    /// Returns the highest of `releases` the policy offers over `installed`, with what
+    /// to do about it, or `None` if there is nothing to offer.
+    pub fn evaluate<'a>(
+        &self,
+        product: Product,
+        installed: &Version,
+        releases: &'a [Version],
+    ) -> Option<(&'a Version, UpdateAction)> {
+        let newer = releases.iter().filter(|v| v.gt(&installed));
+        let floor = self
+            .min_versions
+            .get(&product)
+            .and_then(|v| lenient_version(v));
+        if floor.is_some_and(|floor| installed.lt(&floor)) {
+            tracing::info!("{:?} {} is below the minimum version", product, installed);
+            return newer.max().map(|v| (v, UpdateAction::Force));
+        }
+
+        let req = match self.mode {
+            UpdateMode::Pinned => Some(self.pin_req().ok().flatten()?),
+            _ => None,
+        };
+        let skipped = self.skipped.get(&product).and_then(|v| lenient_version(v));
+        let offered = newer
+            .filter(|v| match self.mode {
+                UpdateMode::Auto | UpdateMode::Notify => true,
+                UpdateMode::Security => v.major == installed.major && v.minor == installed.minor,
+                UpdateMode::Pinned => req.as_ref().is_some_and(|req| req.matches(v)),
+            })
+            .max()?;
+        if skipped.as_ref().is_some_and(|skipped| offered.le(skipped)) {
+            tracing::debug!("{:?} {} is skipped", product, offered);
+            return None;
+        }
+
+        let action = match self.mode {
+            UpdateMode::Auto => UpdateAction::Install,
+            _ => UpdateAction::Notify,
+        };
+        Some((offered, action))
+    }
+}
+
+/// Skips a release of a product until a newer one is out.
+#[tauri::command]
+pub fn skip_update(
+    store: tauri::State<'_, SettingsStore>,
+    product: Product,
+    version: String,
+) -> Result<(), Error> {
+    tracing::info!("handle skip_update: {:?} {}", product, version);
+    if lenient_version(&version).is_none() {
+        return Err(Error::from(anyhow!("Invalid version: {}", version)));
+    }
+    store.update(|s| {
+        s.updates.skipped.insert(product, version.clone());
+    })?;
+    Ok(())
+}
+
+/// Checks the app for updates on start and applies the policy, in place of the
+/// dialog of the Tauri updater.
+pub fn check_app(handle: &tauri::AppHandle) {
+    let handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = update_app(handle).await {
+            tracing::warn!("check app update: {}", e);
+        }
+    });
+}
+
+async fn update_app(handle: tauri::AppHandle) -> Result<(), Error> {
+    let update = handle.updater().check().await?;
+    if !update.is_update_available() {
+        return Ok(());
+    }
+    let installed = update.current_version().clone();
+    let latest = lenient_version(update.latest_version())
+        .ok_or(anyhow!("Invalid app version: {}", update.latest_version()))?;
+
+    let policy = AppContext::from(handle.clone()).settings().updates;
+    let action = policy
+        .evaluate(Product::App, &installed, std::slice::from_ref(&latest))
+        .map(|(_, action)| action)
+        .unwrap_or_default();
+    tracing::info!("app update {} -> {}: {:?}", installed, latest, action);
+    match action {
+        UpdateAction::None => Ok(()),
+        UpdateAction::Install | UpdateAction::Force => {
+            update.download_and_install().await?;
+            handle.restart();
+            Ok(())
+        }
+        UpdateAction::Notify => {
+            let mut dialog = MessageDialogBuilder::new(
+                "Update Available",
+                format!(
+                    "Sonaric {} is available, you have {}.\n\n{}",
+                    latest,
+                    installed,
+                    update.body().map(String::as_str).unwrap_or_default()
+                ),
+            )
+            .buttons(MessageDialogButtons::OkCancelWithLabels(
+                "Install and Restart".to_string(),
+                "Later".to_string(),
+            ));
+            if let Some(window) = handle.get_window("main") {
+                dialog = dialog.parent(&window);
+            }
+            dialog.show(move |install| {
+                if install {
+                    tauri::async_runtime::spawn(async move {
+                        match update.download_and_install().await {
+                            Ok(()) => handle.restart(),
+                            Err(e) => tracing::error!("install app update: {}", e),
+                        }
+                    });
+                } else {
+                    ask_skip(handle, latest);
+                }
+            });
+            Ok(())
+        }
+    }
+}
+
+fn ask_skip(handle: tauri::AppHandle, latest: Version) {
+    let window = handle.get_window("main");
+    dialog::ask(
+        window.as_ref(),
+        "Update Available",
+        format!(
+            "Skip Sonaric {}? You will be notified again when a newer version is out.",
+            latest
+        ),
+        move |skip| {
+            if !skip {
+                return;
+            }
+            let store = handle.state::<SettingsStore>();
+            if let Err(e) = skip_update(store, Product::App, latest.to_string()) {
+                tracing::warn!("skip app update: {}", e);
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: UpdateMode, pin: Option<&str>, skipped: Option<&str>) -> UpdatePolicy {
+        UpdatePolicy {
+            mode,
+            pin: pin.map(str::to_string),
+            skipped: skipped
+                .map(|v| HashMap::from([(Product::Daemon, v.to_string())]))
+                .unwrap_or_default(),
+            min_versions: HashMap::from([(Product::Daemon, "1.2.0".to_string())]),
+        }
+    }
+
+    #[test]
+    fn evaluate() {
+        use UpdateMode::*;
+
+        let releases = ["1.1.0", "1.4.0", "1.4.1", "1.4.3", "1.5.0", "2.0.0"];
+        let releases: Vec<Version> = releases
+            .iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect();
+        let cases = [
+            // (policy, installed, offered)
+            (
+                policy(Auto, None, None),
+                "1.4.0",
+                Some(("2.0.0", UpdateAction::Install)),
+            ),
+            (
+                policy(Notify, None, None),
+                "1.4.0",
+                Some(("2.0.0", UpdateAction::Notify)),
+            ),
+            (policy(Notify, None, None), "2.0.0", None),
+            // the highest patch release, not the latest release
+            (
+                policy(Security, None, None),
+                "1.4.0",
+                Some(("1.4.3", UpdateAction::Notify)),
+            ),
+            (policy(Security, None, None), "1.5.0", None),
+            (
+                policy(Pinned, Some("1.4"), None),
+                "1.4.0",
+                Some(("1.4.3", UpdateAction::Notify)),
+            ),
+            (
+                policy(Pinned, Some("1"), None),
+                "1.4.0",
+                Some(("1.5.0", UpdateAction::Notify)),
+            ),
+            (
+                policy(Pinned, Some(">=1.4.1, <1.5"), None),
+                "1.4.0",
+                Some(("1.4.3", UpdateAction::Notify)),
+            ),
+            (policy(Pinned, Some("2"), None), "2.0.0", None),
+            (policy(Pinned, None, None), "1.4.0", None),
+            // a newer release than the skipped one is offered again
+            (policy(Security, None, Some("1.4.3")), "1.4.0", None),
+            (
+                policy(Notify, None, Some("1.5.0")),
+                "1.4.0",
+                Some(("2.0.0", UpdateAction::Notify)),
+            ),
+            (policy(Pinned, Some("1"), Some("1.5.0")), "1.4.0", None),
+            // below the floor the latest release installs whatever the mode and skips
+            (
+                policy(Pinned, Some("1.1"), Some("2.0.0")),
+                "1.1.0",
+                Some(("2.0.0", UpdateAction::Force)),
+            ),
+        ];
+        for (policy, installed, offered) in cases {
+            let installed = Version::parse(installed).unwrap();
+            let offered = offered.map(|(v, action)| (Version::parse(v).unwrap(), action));
+            assert_eq!(
+                policy
+                    .evaluate(Product::Daemon, &installed, &releases)
+                    .map(|(v, action)| (v.clone(), action)),
+                offered,
+                "{:?} {:?} {}",
+                policy.mode,
+                policy.pin,
+                installed
+            );
+        }
+        assert_eq!(
+            policy(Auto, None, None).evaluate(Product::Daemon, &releases[2], &[]),
+            None
+        );
+    }
+}
@@ -1,17 +1,41 @@
-use crate::helper::{exec_cmd_bash_script, exec_script, try_url, is_wsl_running};
-use crate::{find_sonaric_binary, Error};
+use crate::context::AppContext;
+use crate::helper::try_url;
+use crate::registry::RegistryClient;
+use crate::settings::Channel;
+use crate::update_policy::{Product, UpdateAction};
+use crate::version_cache::{Entry, Fetched, Lookup};
+use crate::Error;
 use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use semver::Version;
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::env;
+use serde_json::Value;
 use std::fmt::{Display, Formatter};
 use tokio::join;
 
 const NA: &str = "n/a";
 
+// patterns of the text output of `sonaric version`
+static VERSION_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^\s*(?:sonaric\s+)?(?:(cli|client|daemon|server)\s+)?version\s*[:=]?\s*(v?\d+(?:\.\d+){1,2}(?:[-+][0-9A-Za-z.+-]*[0-9A-Za-z])?)",
+    )
+    .unwrap()
+});
+static COMMIT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bcommit\s*[:=]\s*([0-9a-f]{6,40})\b").unwrap());
+static BUILT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\b(?:built|build date|date)\s*[:=]\s*([^,\s]+)").unwrap());
+static DAEMON_NOT_RUNNING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)daemon is not running|connection refused|cannot connect to (the )?daemon|failed to connect",
+    )
+    .unwrap()
+});
+
 #[derive(Clone, serde::Serialize)]
 pub struct VersionPayload {
+    pub channel: Channel,
     pub daemon: AppVersion,
     pub gui: AppVersion,
     pub app: AppVersion,
@@ -20,8 +44,34 @@ pub struct VersionPayload {
 #[derive(Clone, serde::Serialize)]
 pub struct AppVersion {
     pub version: String,
+    /// Release offered under the update policy, else the highest known one.
     pub latest: String,
+    /// No update to offer under the update policy.
     pub up_to_date: bool,
+    pub action: UpdateAction,
+}
+
+impl AppVersion {
+    /// Compares the version with the known releases under the update policy.
+    fn new(ctx: &AppContext, product: Product, version: &Version, releases: &[Version]) -> Self {
+        let offer = ctx.settings().updates.evaluate(product, version, releases);
+        let latest = match offer.map(|(v, _)| v).or(releases.iter().max()) {
+            Some(latest) => latest,
+            None => {
+                return Self {
+                    version: version.to_string(),
+                    ..Default::default()
+                }
+            }
+        };
+        let action = offer.map(|(_, action)| action).unwrap_or_default();
+        Self {
+            version: version.to_string(),
+            latest: latest.to_string(),
+            up_to_date: action == UpdateAction::None,
+            action,
+        }
+    }
 }
 
 impl Display for AppVersion {
@@ -29,6 +79,9 @@ impl Display for AppVersion {
         if self.version == NA {
             return write!(f, "{}", self.version);
         }
+        if self.latest == NA {
+            return write!(f, "{} (latest unknown)", self.version);
+        }
         if self.up_to_date {
             write!(f, "{} (up to date)", self.version)
         } else {
@@ -43,6 +96,7 @@ impl Default for AppVersion {
             version: NA.to_string(),
             latest: NA.to_string(),
             up_to_date: true,
+            action: UpdateAction::None,
         }
     }
 }
@@ -50,14 +104,27 @@ impl Default for AppVersion {
 #[tauri::command]
 pub async fn show_version(handle: tauri::AppHandle) -> Result<VersionPayload, Error> {
     tracing::info!("handle show_version");
+    Ok(version_info(handle.into(), Lookup::Cached).await)
+}
+
+/// Like `show_version`, but revalidates the latest versions now.
+#[tauri::command]
+pub async fn refresh_versions(handle: tauri::AppHandle) -> Result<VersionPayload, Error> {
+    tracing::info!("handle refresh_versions");
+    Ok(version_info(handle.into(), Lookup::Refresh).await)
+}
 
+/// Collects the versions of the components, unknown ones are reported as `n/a`.
+/// `lookup` applies to the latest versions, which are cached.
+pub async fn version_info(ctx: AppContext, lookup: Lookup) -> VersionPayload {
     let (app_version, daemon_version, gui_version) = join!(
-        get_app_version(handle.clone()),
-        get_daemon_version(handle.clone()),
-        get_gui_version(),
+        get_app_version(ctx.clone(), lookup),
+        get_daemon_version(ctx.clone(), lookup),
+        get_gui_version(ctx.clone(), lookup),
     );
 
-    Ok(VersionPayload {
+    VersionPayload {
+        channel: ctx.settings().channel,
         app: app_version.unwrap_or_else(|e| {
             tracing::warn!("get app version: {}", e);
             AppVersion::default()
@@ -70,155 +137,328 @@ pub async fn show_version(handle: tauri::AppHandle) -> Result<VersionPayload, Er
             tracing::warn!("get gui version: {}", e);
             AppVersion::default()
         }),
-    })
+    }
 }
 
-pub async fn get_app_version(handle: tauri::AppHandle) -> Result<AppVersion, Error> {
-    let resp = handle.updater().check().await?;
+pub async fn get_app_version(ctx: AppContext, lookup: Lookup) -> Result<AppVersion, Error> {
+    let version = ctx.package_info().version.clone();
+    // the updater needs the app, a headless run only knows its own version
+    let handle = match &ctx {
+        AppContext::App(handle) => handle.clone(),
+        AppContext::Headless(_) => {
+            return Ok(AppVersion::new(&ctx, Product::App, &version, &[]));
+        }
+    };
+    let latest = ctx
+        .version_cache()
+        .get("app-updater", lookup, move |_| {
+            Box::pin(async move {
+                let resp = handle.updater().check().await?;
+                Ok(Fetched::Modified(Entry::new(
+                    resp.latest_version().to_string(),
+                )))
+            })
+        })
+        .await?;
 
-    Ok(AppVersion {
-        version: resp.current_version().to_string(),
-        latest: resp.latest_version().to_string(),
-        up_to_date: !resp.is_update_available(),
-    })
+    Ok(AppVersion::new(
+        &ctx,
+        Product::App,
+        &version,
+        &Vec::from_iter(lenient_version(&latest)),
+    ))
 }
 
-pub async fn get_daemon_version(handle: tauri::AppHandle) -> Result<AppVersion, Error> {
-    match env::consts::OS {
-        "macos" | "linux" => {
-            let binary_path = match find_sonaric_binary() {
-                Some(p) => p,
-                None => return Err(Error::from(anyhow!("Sonaric binary not found"))),
-            };
-            let binary_path_str = binary_path.to_str().ok_or(anyhow!("Invalid binary path"))?;
-
-            match exec_script(
-                handle.clone(),
-                binary_path_str,
-                vec!["version"],
-                false,
-                false,
-            )
-            .await
-            {
-                Ok(res) => {
-                    if res.contains("version") {
-                        // sonaric is installed, check version
-                        let version = parse_version(res.clone())?;
-                        let latest_version = get_latest_version().await?;
-
-                        Ok(AppVersion {
-                            version: version.to_string(),
-                            latest: latest_version.to_string(),
-                            up_to_date: !latest_version.gt(&version),
-                        })
-                    } else {
-                        // sonaric is not installed
-                        Err(Error::from(anyhow!("Sonaric is not installed")))
-                    }
-                }
-                Err(e) => return Err(Error::from(e)),
-            }
-        }
-        "windows" => {
-            if !is_wsl_running().await {
-                return Err(Error::from(anyhow!("WSL distribution is not running")));
-            }
+pub async fn get_daemon_version(ctx: AppContext, lookup: Lookup) -> Result<AppVersion, Error> {
+    let version = ctx.platform().daemon_version(ctx.clone()).await?;
+    // only the latest release of the channel is published
+    let latest_version = try_latest_version(&ctx, lookup).await;
+    Ok(AppVersion::new(
+        &ctx,
+        Product::Daemon,
+        &version,
+        &Vec::from_iter(latest_version),
+    ))
+}
 
-            let res = exec_cmd_bash_script(vec![
-                "/C",
-                "wsl",
-                "--distribution",
-                "Ubuntu-22.04",
-                "--user",
-                "root",
-                "--exec",
-                "/bin/bash",
-                "-c",
-                "sonaric version",
-            ])
-            .await?;
-            if !res.stdout.contains("version") {
-                return Err(Error::from(anyhow!("Sonaric is not installed")));
-            }
-            let version = parse_version(res.stdout.clone())?;
-            let latest_version = get_latest_version().await?;
+pub async fn get_gui_version(ctx: AppContext, lookup: Lookup) -> Result<AppVersion, Error> {
+    let settings = ctx.settings();
+    let body = try_url(&format!("{}/version", settings.api_url())).await?;
+    let ver = Version::parse(body.trim().trim_start_matches("v"))?;
 
-            Ok(AppVersion {
-                version: version.to_string(),
-                latest: latest_version.to_string(),
-                up_to_date: !latest_version.gt(&version),
+    // the releases are the version tags up to the highest one of the image tagged "latest"
+    let repo_url = settings.gui_registry_url.clone();
+    let releases = ctx
+        .version_cache()
+        .get(&format!("{}:latest", repo_url), lookup, move |_| {
+            Box::pin(async move {
+                let releases = RegistryClient::new(&repo_url)
+                    .releases_up_to("latest")
+                    .await?;
+                let body: Vec<String> = releases.iter().map(Version::to_string).collect();
+                Ok(Fetched::Modified(Entry::new(body.join(" "))))
             })
-        }
-        _ => return Err(Error::from(anyhow!("Unsupported OS"))),
-    }
+        })
+        .await
+        .and_then(|body| {
+            body.split_whitespace()
+                .map(|v| Ok(Version::parse(v)?))
+                .collect::<Result<Vec<_>, Error>>()
+        });
+    let releases = releases
+        .map_err(|e| tracing::warn!("get latest gui version: {}", e))
+        .unwrap_or_default();
+    Ok(AppVersion::new(&ctx, Product::Gui, &ver, &releases))
 }
 
-#[derive(Deserialize, Debug)]
-struct Tags {
-    manifest: HashMap<String, Manifest>,
+/// Build of a Sonaric component, as printed by `sonaric version`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct BuildInfo {
+    pub version: Version,
+    pub commit: Option<String>,
+    pub built: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
-struct Manifest {
-    tag: Vec<String>,
+/// Parsed output of `sonaric version`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct VersionReport {
+    pub cli: Option<BuildInfo>,
+    pub daemon: Option<BuildInfo>,
+    /// Whether the CLI could reach the daemon.
+    pub daemon_running: bool,
 }
 
-pub async fn get_gui_version() -> Result<AppVersion, Error> {
-    let url = "http://127.0.0.1:44005/version";
-    let body = try_url(url).await?;
-    let ver = Version::parse(body.trim().trim_start_matches("v"))?;
+impl VersionReport {
+    /// Parses the JSON output of newer CLIs or the text output, e.g.
+    /// `CLI version: v1.1.0, commit: 9c1f0e2, built: 2024-05-20T10:12:44Z`,
+    /// followed by a `Daemon version: ...` line or the error of the CLI.
+    pub fn parse(output: &str) -> Result<Self, Error> {
+        let mut report = parse_json(output).unwrap_or_else(|| parse_text(output));
+        if report.cli.is_none() && report.daemon.is_none() {
+            return Err(Error::from(anyhow!(
+                "Version not found in {:?}",
+                output.trim()
+            )));
+        }
 
-    // get latest version from https://us-central1-docker.pkg.dev/v2/sonaric-platform/sonaric-public/sonaric-gui/tags/list
-    let latest_body = try_url(
-        "https://us-central1-docker.pkg.dev/v2/sonaric-platform/sonaric-public/sonaric-gui/tags/list",
-    ).await?;
-
-    let tags: Tags = serde_json::from_str(&latest_body)?;
-    for (_, manifest) in tags.manifest.iter() {
-        if manifest.tag.contains(&"latest".to_string()) {
-            // get the first tag that is not "latest"
-            let latest = manifest
-                .tag
-                .iter()
-                .find(|t| t.starts_with("v"))
-                .ok_or(anyhow!("GUI release not found in registry"))?;
-            let latest_ver = Version::parse(latest.trim().trim_start_matches("v"))?;
-            return Ok(AppVersion {
-                version: ver.to_string(),
-                latest: latest_ver.to_string(),
-                up_to_date: !latest_ver.gt(&ver),
-            });
+        // older CLIs only print their own version
+        report.daemon_running = report.daemon.is_some() || !DAEMON_NOT_RUNNING.is_match(output);
+        Ok(report)
+    }
+
+    /// Installed version, the one of the CLI, which is released with the daemon.
+    pub fn installed(&self) -> &Version {
+        match (&self.cli, &self.daemon) {
+            (Some(info), _) | (None, Some(info)) => &info.version,
+            (None, None) => unreachable!("the report has a version"),
         }
     }
-    Ok(AppVersion {
-        version: ver.to_string(),
-        latest: NA.to_string(),
-        up_to_date: true,
+}
+
+fn parse_json(output: &str) -> Option<VersionReport> {
+    let body = output.get(output.find('{')?..=output.rfind('}')?)?;
+    let value: Value = serde_json::from_str(body).ok()?;
+
+    let cli = ["cli", "client"]
+        .iter()
+        .find_map(|key| build_info(&value[*key]))
+        .or_else(|| build_info(&value));
+    let daemon = ["daemon", "server"]
+        .iter()
+        .find_map(|key| build_info(&value[*key]));
+    Some(VersionReport {
+        cli,
+        daemon,
+        daemon_running: false,
     })
 }
 
-pub fn parse_version(text: String) -> Result<Version, Error> {
-    let prefix = "CLI version:";
-    let suffix = ", ";
+fn build_info(value: &Value) -> Option<BuildInfo> {
+    let field = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| value.get(*key)?.as_str().map(str::to_string))
+    };
+    Some(BuildInfo {
+        version: lenient_version(&field(&["version"])?)?,
+        commit: field(&["commit", "git_commit", "revision"]),
+        built: field(&["built", "build_date", "date"]),
+    })
+}
 
-    let start = text
-        .find(prefix)
-        .ok_or(anyhow!("Version start not found"))?;
-    let end = text[start..]
-        .find(suffix)
-        .ok_or(anyhow!("Version end not found"))?;
+fn parse_text(output: &str) -> VersionReport {
+    let mut report = VersionReport {
+        cli: None,
+        daemon: None,
+        daemon_running: false,
+    };
+    for line in output.lines() {
+        let caps = match VERSION_LINE.captures(line) {
+            Some(caps) => caps,
+            None => continue,
+        };
+        let version = match lenient_version(&caps[2]) {
+            Some(version) => version,
+            None => continue,
+        };
+        let info = BuildInfo {
+            version,
+            commit: COMMIT.captures(line).map(|c| c[1].to_string()),
+            built: BUILT.captures(line).map(|c| c[1].to_string()),
+        };
+        let component = caps.get(1).map(|c| c.as_str().to_lowercase());
+        match component.as_deref() {
+            Some("daemon" | "server") => report.daemon = Some(info),
+            _ if report.cli.is_none() => report.cli = Some(info),
+            _ => {}
+        }
+    }
+    report
+}
 
-    let ver = text[start + prefix.len()..start + end]
-        .trim()
-        .trim_start_matches("v");
+/// Parses `v1.2.3`, `1.2.3-rc.1` and `1.2` as semver.
+pub(crate) fn lenient_version(text: &str) -> Option<Version> {
+    let text = text.trim().trim_start_matches('v');
+    Version::parse(text)
+        .or_else(|_| Version::parse(&format!("{}.0", text)))
+        .ok()
+}
 
-    Version::parse(ver).map_err(Error::from)
+/// Returns the installed version from the output of `sonaric version`.
+pub fn parse_version(text: String) -> Result<Version, Error> {
+    Ok(VersionReport::parse(&text)?.installed().clone())
 }
 
-pub async fn get_latest_version() -> Result<Version, Error> {
-    let url = "https://storage.googleapis.com/sonaric-releases/stable/linux/latest-version";
-    let body = try_url(url).await?;
+pub async fn get_latest_version(ctx: &AppContext, lookup: Lookup) -> Result<Version, Error> {
+    let url = ctx.settings().latest_version_url();
+    let body = ctx.version_cache().get_url(&url, lookup).await?;
     let ver = Version::parse(body.trim().trim_start_matches("v"))?;
     Ok(ver)
 }
+
+/// Returns the latest daemon version, or `None` if it can't be fetched, e.g. offline.
+pub async fn try_latest_version(ctx: &AppContext, lookup: Lookup) -> Option<Version> {
+    match get_latest_version(ctx, lookup).await {
+        Ok(v) => Some(v),
+        Err(e) => {
+            tracing::warn!("get latest version: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(version: &str, commit: Option<&str>, built: Option<&str>) -> Option<BuildInfo> {
+        Some(BuildInfo {
+            version: Version::parse(version).unwrap(),
+            commit: commit.map(str::to_string),
+            built: built.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn parse_report() {
+        let v110 = info("1.1.0", Some("9c1f0e2"), Some("2024-05-20T10:12:44Z"));
+        let cases = [
+            // (output, cli, daemon, daemon running)
+            (
+                "CLI version: v1.1.0, commit: 9c1f0e2, built: 2024-05-20T10:12:44Z\n\
+                 Daemon version: v1.1.0, commit: 9c1f0e2, built: 2024-05-20T10:12:44Z\n",
+                v110.clone(),
+                v110.clone(),
+                true,
+            ),
+            (
+                "CLI version: v1.1.0, commit: 9c1f0e2, built: 2024-05-20T10:12:44Z\n\
+                 \nError: daemon is not running\n",
+                v110.clone(),
+                None,
+                false,
+            ),
+            (
+                r#"{"cli": {"version": "v1.2.0", "commit": "abc1234", "built": "2024-06-01"},
+                    "daemon": {"version": "1.2.0", "git_commit": "abc1234", "build_date": "2024-06-01"}}"#,
+                info("1.2.0", Some("abc1234"), Some("2024-06-01")),
+                info("1.2.0", Some("abc1234"), Some("2024-06-01")),
+                true,
+            ),
+            (
+                "level=info msg=\"loading config\"\n\
+                 {\"client\": {\"version\": \"1.2\"}, \"server\": {\"version\": \"1.2.1\", \"date\": \"2024-06-02\"}}",
+                info("1.2.0", None, None),
+                info("1.2.1", None, Some("2024-06-02")),
+                true,
+            ),
+            (
+                r#"{"version": "1.3.0-rc.1", "revision": "deadbeef"}"#,
+                info("1.3.0-rc.1", Some("deadbeef"), None),
+                None,
+                true,
+            ),
+            // braces of an error before the text output
+            (
+                "Error: desc = x } retry {\nCLI version: v1.1.0",
+                info("1.1.0", None, None),
+                None,
+                true,
+            ),
+            // partial text of older CLIs
+            ("sonaric version 1.0", info("1.0.0", None, None), None, true),
+            (
+                "CLI version: v1.0.3, commit: 1a2b3c4d\nError: dial tcp 127.0.0.1:44006: connection refused",
+                info("1.0.3", Some("1a2b3c4d"), None),
+                None,
+                false,
+            ),
+            (
+                "Daemon version: v1.1.0+wsl",
+                None,
+                info("1.1.0+wsl", None, None),
+                true,
+            ),
+        ];
+        for (output, cli, daemon, daemon_running) in cases {
+            let report = VersionReport::parse(output).unwrap();
+            assert_eq!(
+                report,
+                VersionReport {
+                    cli,
+                    daemon,
+                    daemon_running
+                },
+                "{}",
+                output
+            );
+        }
+    }
+
+    #[test]
+    fn parse_report_garbage() {
+        for output in [
+            "",
+            "bash: sonaric: command not found",
+            "CLI version: unknown",
+            r#"{"cli": {"version": "dev"}}"#,
+            "{not json",
+            "Error: desc = x } retry {",
+        ] {
+            assert!(VersionReport::parse(output).is_err(), "{}", output);
+        }
+    }
+
+    #[test]
+    fn installed_version() {
+        let cases = [
+            ("CLI version: v1.1.0\nDaemon version: v1.0.9", "1.1.0"),
+            ("Daemon version: v1.0.9", "1.0.9"),
+        ];
+        for (output, installed) in cases {
+            assert_eq!(
+                parse_version(output.to_string()).unwrap(),
+                Version::parse(installed).unwrap()
+            );
+        }
+    }
+}
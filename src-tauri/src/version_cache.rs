@@ -0,0 +1,304 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+pub const CACHE_FILE: &str = "version-cache.json";
+
+/// Age after which an entry is revalidated.
+const TTL: Duration = Duration::from_secs(15 * 60);
+/// Lookups of a key aren't retried sooner after a failure, e.g. when offline.
+const RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// How a lookup may use the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lookup {
+    /// Fresh entries from the cache, stale ones are returned and revalidated in the
+    /// background, missing ones are fetched.
+    Cached,
+    /// Never waits for the network, stale and missing entries are fetched in the
+    /// background for the next lookup.
+    CacheOnly,
+    /// Revalidates the entry now.
+    Refresh,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub body: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Seconds since the epoch.
+    fetched_at: u64,
+}
+
+impl Entry {
+    pub fn new(body: String) -> Self {
+        Self {
+            body,
+            etag: None,
+            last_modified: None,
+            fetched_at: unix_time(),
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        unix_time().saturating_sub(self.fetched_at) < TTL.as_secs()
+    }
+}
+
+pub enum Fetched {
+    Modified(Entry),
+    NotModified,
+}
+
+pub type Fetch = Pin<Box<dyn Future<Output = Result<Fetched, Error>> + Send>>;
+
+/// Cache of the "latest version" lookups, persisted across restarts.
+#[derive(Clone, Default)]
+pub struct VersionCache(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    path: Option<PathBuf>,
+    entries: Mutex<HashMap<String, Entry>>,
+    failures: Mutex<HashMap<String, Instant>>,
+    revalidating: Mutex<HashSet<String>>,
+}
+
+impl VersionCache {
+    /// Loads the cache from `path`, starts empty if it's missing or can't be parsed.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let entries = match path.as_ref().map(std::fs::read_to_string) {
+            Some(Ok(body)) => serde_json::from_str(&body).unwrap_or_else(|e| {
+                tracing::warn!("parse version cache {:?}: {}", path, e);
+                HashMap::new()
+            }),
+            _ => HashMap::new(),
+        };
+        Self(Arc::new(Inner {
+            path,
+            entries: Mutex::new(entries),
+            ..Default::default()
+        }))
+    }
+
+    /// Returns the body of `url`, sending the validators of the cached entry.
+    pub async fn get_url(&self, url: &str, lookup: Lookup) -> Result<String, Error> {
+        let url = url.to_string();
+        self.get(&url.clone(), lookup, move |cached| http_get(url, cached))
+            .await
+    }
+
+    /// Returns the value of `key`, `fetch` receives the cached entry to revalidate.
+    pub async fn get<F>(&self, key: &str, lookup: Lookup, fetch: F) -> Result<String, Error>
+    where
+        F: FnOnce(Option<Entry>) -> Fetch,
+    {
+        let cached = self.0.entries.lock().unwrap().get(key).cloned();
+        match (lookup, cached) {
+            (Lookup::Cached, Some(entry)) if entry.is_fresh() => Ok(entry.body),
+            (Lookup::Cached, Some(entry)) => {
+                if !self.backing_off(key) {
+                    self.revalidate(key, fetch(Some(entry.clone())), Some(entry.clone()));
+                }
+                Ok(entry.body)
+            }
+            (Lookup::CacheOnly, cached) => {
+                let fresh = cached.as_ref().is_some_and(Entry::is_fresh);
+                if !fresh && !self.backing_off(key) {
+                    self.revalidate(key, fetch(cached.clone()), cached.clone());
+                }
+                cached
+                    .map(|entry| entry.body)
+                    .ok_or(Error::from(anyhow!("{} is not cached yet", key)))
+            }
+            (Lookup::Cached, None) if self.backing_off(key) => Err(Error::from(anyhow!(
+                "{} failed recently, retrying later",
+                key
+            ))),
+            (_, cached) => self.update(key, fetch(cached.clone()), cached).await,
+        }
+    }
+
+    async fn update(
+        &self,
+        key: &str,
+        fetch: Fetch,
+        cached: Option<Entry>,
+    ) -> Result<String, Error> {
+        let entry = match (fetch.await, cached) {
+            (Ok(Fetched::Modified(entry)), _) => entry,
+            (Ok(Fetched::NotModified), Some(cached)) => Entry {
+                fetched_at: unix_time(),
+                ..cached
+            },
+            (Ok(Fetched::NotModified), None) => {
+                return Err(Error::from(anyhow!("{} not modified but not cached", key)))
+            }
+            (Err(e), cached) => {
+                self.0
+                    .failures
+                    .lock()
+                    .unwrap()
+                    .insert(key.to_string(), Instant::now());
+                return match cached {
+                    Some(cached) => {
+                        tracing::warn!("revalidate {}: {}, using the cached value", key, e);
+                        Ok(cached.body)
+                    }
+                    None => Err(e),
+                };
+            }
+        };
+
+        self.0.failures.lock().unwrap().remove(key);
+        let body = entry.body.clone();
+        self.0
+            .entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), entry);
+        self.save();
+        Ok(body)
+    }
+
+    /// Updates the entry in the background, once at a time per key.
+    fn revalidate(&self, key: &str, fetch: Fetch, cached: Option<Entry>) {
+        if !self.0.revalidating.lock().unwrap().insert(key.to_string()) {
+            return;
+        }
+        let cache = self.clone();
+        let key = key.to_string();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = cache.update(&key, fetch, cached).await {
+                tracing::debug!("revalidate {}: {}", key, e);
+            }
+            cache.0.revalidating.lock().unwrap().remove(&key);
+        });
+    }
+
+    fn backing_off(&self, key: &str) -> bool {
+        match self.0.failures.lock().unwrap().get(key) {
+            Some(at) => at.elapsed() < RETRY_AFTER,
+            None => false,
+        }
+    }
+
+    fn save(&self) {
+        let path = match &self.0.path {
+            Some(path) => path,
+            None => return,
+        };
+        let body = match serde_json::to_string_pretty(&*self.0.entries.lock().unwrap()) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::warn!("serialize version cache: {}", e);
+                return;
+            }
+        };
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(path, body) {
+            tracing::warn!("save version cache {:?}: {}", path, e);
+        }
+    }
+}
+
+fn http_get(url: String, cached: Option<Entry>) -> Fetch {
+    Box::pin(async move {
+        let mut req = reqwest::Client::new().get(&url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                req = req.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                req = req.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let resp = req.send().await?;
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            tracing::debug!("{} not modified", url);
+            return Ok(Fetched::NotModified);
+        }
+        let resp = resp.error_for_status()?;
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        Ok(Fetched::Modified(Entry {
+            etag,
+            last_modified,
+            ..Entry::new(resp.text().await?)
+        }))
+    })
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn stale_cache(key: &str) -> VersionCache {
+        let cache = VersionCache::load(None);
+        let entry = Entry {
+            fetched_at: 0,
+            ..Entry::new("1.4.0".to_string())
+        };
+        cache
+            .0
+            .entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), entry);
+        cache
+    }
+
+    #[tokio::test]
+    async fn stale_lookups_back_off_after_a_failure() {
+        let key = "https://example.com/latest";
+        for lookup in [Lookup::Cached, Lookup::CacheOnly] {
+            let cache = stale_cache(key);
+            let fetches = Arc::new(AtomicUsize::new(0));
+            let failing = || {
+                let fetches = fetches.clone();
+                move |_| -> Fetch {
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    Box::pin(async { Err(Error::from(anyhow!("offline"))) })
+                }
+            };
+
+            assert_eq!(cache.get(key, lookup, failing()).await.unwrap(), "1.4.0");
+            while cache.0.revalidating.lock().unwrap().contains(key) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert!(cache.backing_off(key), "{:?}", lookup);
+
+            assert_eq!(cache.get(key, lookup, failing()).await.unwrap(), "1.4.0");
+            assert_eq!(fetches.load(Ordering::SeqCst), 1, "{:?}", lookup);
+        }
+    }
+}
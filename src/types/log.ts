@@ -0,0 +1,30 @@
+// Mirrors the types of src-tauri/src/logfile.rs and src-tauri/src/logview.rs
+export type LogFile = {
+  path: string
+  size: number
+  modified: number
+}
+
+export type LogSource = 'app' | 'daemon'
+
+export type LogLevel = 'trace' | 'debug' | 'info' | 'warn' | 'error'
+
+export type LogFilter = {
+  level?: LogLevel
+  pattern?: string
+}
+
+export type LogPage = {
+  path: string
+  lines: string[]
+  // pass as `before` to load earlier lines, null at the start of the file
+  before: number | null
+}
+
+// payload of the `log-lines` event
+export type LogLines = {
+  id: number
+  source: LogSource
+  lines: string[]
+  reset: boolean
+}
@@ -0,0 +1,13 @@
+// Mirrors `NodeState` in src-tauri/src/state.rs
+export type Backend = 'linux' | 'macos' | 'wsl'
+
+export type Component = 'wsl' | 'wsl2' | 'distribution' | 'sonaric'
+
+// Mirrors `UpdateAction` in src-tauri/src/update_policy.rs
+export type UpdateAction = 'none' | 'notify' | 'install' | 'force'
+
+export type NodeState =
+  | { state: 'install', backend: Backend, missing: Component }
+  | { state: 'start', backend: Backend, installed: string | null, daemon: boolean, gui: boolean }
+  | { state: 'update', backend: Backend, installed: string, latest: string, daemon: boolean, action: UpdateAction }
+  | { state: 'ready', backend: Backend, installed: string | null, latest: string | null }
@@ -0,0 +1,15 @@
+// Mirrors `OperationInfo` and `OperationFinished` in src-tauri/src/operation.rs
+export type OperationKind = 'install' | 'start' | 'stop' | 'uninstall' | 'rollback'
+
+export type Operation = {
+  id: number
+  kind: OperationKind
+  state: 'queued' | 'running'
+}
+
+export type OperationFinished = {
+  id: number
+  kind: OperationKind
+  outcome: 'succeeded' | 'failed' | 'cancelled'
+  error: string | null
+}
@@ -0,0 +1,57 @@
+// Mirrors `Settings` in src-tauri/src/settings.rs
+export type Channel = 'stable' | 'beta' | 'nightly'
+
+export type Consent = 'unknown' | 'granted' | 'denied'
+
+export type Settings = {
+  version: number
+  channel: Channel
+  gui_port: number
+  api_port: number
+  wsl_distribution: string
+  sentry_dsn: string
+  telemetry: Consent
+  feedback_url: string
+  releases_url: string
+  gui_registry_url: string
+  daemon_log_path: string | null
+  verified_downloads: boolean
+  logs: LogSettings
+  redaction: RedactionSettings
+  updates: UpdatePolicy
+}
+
+export type LogSettings = {
+  rotation: 'daily' | 'size'
+  max_size_mb: number
+  max_files: number
+  console_level: string
+  file_level: string
+}
+
+export type LogTarget = 'console' | 'file'
+
+export type RedactionRule = {
+  name: string
+  pattern: string
+  replacement: string
+}
+
+export type RedactionSettings = {
+  enabled: boolean
+  disabled_rules: string[]
+  rules: RedactionRule[]
+}
+
+export type Product = 'app' | 'daemon' | 'gui'
+
+export type UpdatePolicy = {
+  mode: 'auto' | 'notify' | 'security' | 'pinned'
+  pin: string | null
+  skipped: Partial<Record<Product, string>>
+  min_versions: Partial<Record<Product, string>>
+}
+
+export type BugReport =
+  | { kind: 'sent' }
+  | { kind: 'saved', path: string }